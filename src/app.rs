@@ -1,144 +1,1075 @@
 use anyhow::Result;
 #[allow(unused_imports)]
 use log::{error, info, log, trace, warn};
-use std::{fs::File, io::Read, sync::mpsc};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, sync::mpsc};
 
-use crate::interpreter::{Chip8, Interpreter, InterpreterError, Superchip};
+use crate::audio::{self, AudioBackend, CaptureBackend, Tone, Waveform};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::capture::{self, GifRecorder};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::cli::Args;
+use crate::database::{Database, Metadata};
+use crate::interpreter::{
+    self, BigFont, Chip8, Chip8X, ErrorKind, HiresChip8, Interpreter, InterpreterError, Keypad,
+    MachineConfig, Megachip, Quirks, SmallFont, Superchip, SuperchipVersion, Variant,
+    MAX_MEMORY_SIZE,
+};
+use crate::keymap::{Keymap, Preset, VIP_LAYOUT};
+use crate::library::Library;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::movie::{self, Movie};
+use crate::post_mortem::{FaultPolicy, PostMortem};
+use crate::settings::{Override, Overrides, Palette, Settings};
+use crate::touch_keypad::{TouchKeypad, Visibility};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Mode {
+// Resets in a row for faults at the same address before halting instead.
+const MAX_RESETS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(clap::ValueEnum))]
+pub enum Mode {
     Chip8,
+    // SUPER-CHIP as modern interpreters like Octo run it.
+    #[cfg_attr(not(target_arch = "wasm32"), value(name = "superchip"))]
     SuperChip,
+    #[cfg_attr(not(target_arch = "wasm32"), value(name = "superchip-1.0"))]
+    SuperChip10,
+    #[cfg_attr(not(target_arch = "wasm32"), value(name = "superchip-1.1"))]
+    SuperChip11,
+    HiresChip8,
+    #[cfg_attr(not(target_arch = "wasm32"), value(name = "chip8x"))]
+    Chip8X,
+    #[cfg_attr(not(target_arch = "wasm32"), value(name = "megachip"))]
+    MegaChip,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 7] = [
+        Mode::Chip8,
+        Mode::SuperChip,
+        Mode::SuperChip10,
+        Mode::SuperChip11,
+        Mode::HiresChip8,
+        Mode::Chip8X,
+        Mode::MegaChip,
+    ];
+
+    pub fn default_quirks(self) -> Quirks {
+        match self {
+            Mode::Chip8 | Mode::HiresChip8 | Mode::Chip8X => Quirks::CHIP8,
+            Mode::SuperChip | Mode::SuperChip11 | Mode::MegaChip => Quirks::SUPERCHIP,
+            Mode::SuperChip10 => Quirks::SUPERCHIP_1_0,
+        }
+    }
+
+    pub fn interpreter(
+        self,
+        data: &[u8],
+        quirks: Quirks,
+        machine: &MachineConfig,
+        seed: u64,
+    ) -> Result<Box<dyn Interpreter>, ErrorKind> {
+        Ok(match self {
+            Mode::Chip8 => Box::new(
+                Chip8::new(data, machine)?
+                    .with_quirks(quirks)
+                    .with_seed(seed),
+            ),
+            Mode::SuperChip => Box::new(
+                Superchip::new(data, machine)?
+                    .with_quirks(quirks)
+                    .with_seed(seed),
+            ),
+            Mode::SuperChip10 => Box::new(
+                Superchip::new(data, machine)?
+                    .with_quirks(quirks)
+                    .with_seed(seed)
+                    .with_version(SuperchipVersion::V1_0),
+            ),
+            Mode::SuperChip11 => Box::new(
+                Superchip::new(data, machine)?
+                    .with_quirks(quirks)
+                    .with_seed(seed)
+                    .with_version(SuperchipVersion::V1_1),
+            ),
+            Mode::HiresChip8 => Box::new(
+                HiresChip8::new(data, machine)?
+                    .with_quirks(quirks)
+                    .with_seed(seed),
+            ),
+            Mode::Chip8X => Box::new(
+                Chip8X::new(data, machine)?
+                    .with_quirks(quirks)
+                    .with_seed(seed),
+            ),
+            Mode::MegaChip => Box::new(
+                Megachip::new(data, machine)?
+                    .with_quirks(quirks)
+                    .with_seed(seed),
+            ),
+        })
+    }
+}
+
+// XO-CHIP is not supported, so its programs run on the closest variant.
+impl From<Variant> for Mode {
+    fn from(variant: Variant) -> Self {
+        match variant {
+            Variant::Chip8 => Mode::Chip8,
+            Variant::HiresChip8 => Mode::HiresChip8,
+            Variant::Chip8X => Mode::Chip8X,
+            Variant::MegaChip => Mode::MegaChip,
+            Variant::SuperChip | Variant::XoChip => Mode::SuperChip,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Tab {
     Controls,
+    Library,
     Logs,
 }
 
 #[derive(Clone, PartialEq, Eq)]
-struct Program {
+pub struct Program {
     filename: String,
+    path: Option<PathBuf>,
     data: Vec<u8>,
+    hash: String,
 }
 
 impl Program {
-    fn new(filename: impl Into<String>, data: &[u8]) -> Result<Self> {
+    pub fn new(filename: impl Into<String>, data: &[u8]) -> Result<Self> {
         Ok(Self {
             filename: filename.into(),
+            path: None,
             data: data.to_vec(),
+            hash: sha1_smol::Sha1::from(data).digest().to_string(),
         })
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_path(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let filename = path
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let data = std::fs::read(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+
+        let mut program = Self::new(filename, &data)?;
+        program.path = Some(path.to_path_buf());
+        Ok(program)
+    }
+
+    fn from_dropped_file(file: &egui::DroppedFile) -> Result<Self> {
+        match (&file.bytes, &file.path) {
+            (Some(bytes), _) => Self::new(file.name.clone(), bytes),
+            #[cfg(not(target_arch = "wasm32"))]
+            (None, Some(path)) => Self::from_path(path),
+            _ => Err(anyhow::anyhow!(
+                "Dropped file {} has no contents",
+                file.name
+            )),
+        }
+    }
+
+    // Accepts opcodes written as hex, optionally prefixed with `0x` and separated by whitespace
+    // or commas, e.g. `00E0 A22A 600C` or `0x00, 0xE0`.
+    fn from_hex(text: &str) -> Result<Self> {
+        let digits = text
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .map(|token| token.trim_start_matches("0x").trim_start_matches("0X"))
+            .collect::<String>();
+
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            anyhow::bail!("Pasted program is not valid hex");
+        }
+        if digits.is_empty() || digits.len() % 2 != 0 {
+            anyhow::bail!("Pasted program must contain an even, non-zero number of hex digits");
+        }
+
+        let data = (0..digits.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).expect("Digits were validated"))
+            .collect::<Vec<u8>>();
+
+        Self::new("clipboard", &data)
+    }
+
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
 }
 
 pub struct Octarou {
     interpreter: Option<Box<dyn Interpreter>>,
-    mode: Mode,
-    speed: u64,
+    keypad: Keypad,
+    touch_keypad: TouchKeypad,
+    fault_policy: FaultPolicy,
+    post_mortem: Option<PostMortem>,
+    // Address of the last fault that reset the program, and how many times in a row it did.
+    resets: Option<(usize, usize)>,
     current_program: Option<Program>,
+    database: Option<Database>,
+    metadata: Option<Metadata>,
+    settings: Settings,
+    overrides: Overrides,
 
     screen_size: egui::Vec2,
+    // Texture for variants that draw in true colour.
+    screen_texture: Option<egui::TextureHandle>,
     current_tab: Tab,
 
+    rebinding: Option<u8>,
+
+    library: Option<Library>,
+    library_selection: Option<PathBuf>,
+    library_channel: (mpsc::Sender<LibraryScan>, mpsc::Receiver<LibraryScan>),
+
     file_dialog_channel: (mpsc::Sender<Program>, mpsc::Receiver<Program>),
+    folder_dialog_channel: (mpsc::Sender<PathBuf>, mpsc::Receiver<PathBuf>),
+    font_dialog_channel: (mpsc::Sender<FontFile>, mpsc::Receiver<FontFile>),
+
+    audio: Box<dyn AudioBackend>,
+    audio_recording: Option<AudioRecording>,
+    #[cfg(not(target_arch = "wasm32"))]
+    gif_recording: Option<GifRecorder>,
+    recording_dialog_channel: (mpsc::Sender<PathBuf>, mpsc::Receiver<PathBuf>),
+    #[cfg(not(target_arch = "wasm32"))]
+    movie: Option<MovieState>,
+    // Egui time at which the next movie frame is due.
+    #[cfg(not(target_arch = "wasm32"))]
+    next_movie_frame: f64,
+    // Keys released since the last movie frame, so that releases between frames are not lost.
+    #[cfg(not(target_arch = "wasm32"))]
+    movie_keys_released: [bool; 16],
+    #[cfg(not(target_arch = "wasm32"))]
+    movie_dialog_channel: (mpsc::Sender<PathBuf>, mpsc::Receiver<PathBuf>),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FontSlot {
+    Small,
+    Big,
+}
+
+struct FontFile {
+    slot: FontSlot,
+    data: Vec<u8>,
+}
+
+struct LibraryScan {
+    directory: PathBuf,
+    result: std::io::Result<Library>,
+}
 
-    #[allow(unused)]
-    stream: (rodio::OutputStream, rodio::OutputStreamHandle),
-    sink: rodio::Sink,
-    muted: bool,
+struct AudioRecording {
+    path: PathBuf,
+    capture: CaptureBackend,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+enum MovieState {
+    Recording(Movie),
+    Playing { movie: Movie, frame: usize },
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl MovieState {
+    fn movie(&self) -> &Movie {
+        match self {
+            MovieState::Recording(movie) | MovieState::Playing { movie, .. } => movie,
+        }
+    }
 }
 
 impl Default for Octarou {
     fn default() -> Self {
-        let (stream, handle) = rodio::OutputStream::try_default().unwrap();
-        let source = rodio::source::SineWave::new(329.628);
-        let sink = rodio::Sink::try_new(&handle).unwrap();
-        sink.append(source);
-        sink.pause();
-
         Self {
             interpreter: None,
-            mode: Mode::Chip8,
-            speed: 700,
+            keypad: Keypad::default(),
+            touch_keypad: TouchKeypad::default(),
+            fault_policy: FaultPolicy::Halt,
+            post_mortem: None,
+            resets: None,
             current_program: None,
+            database: None,
+            metadata: None,
+            settings: Settings::default(),
+            overrides: Overrides::default(),
 
             screen_size: egui::Vec2::ZERO,
+            screen_texture: None,
             current_tab: Tab::Controls,
 
+            rebinding: None,
+
+            library: None,
+            library_selection: None,
+            library_channel: mpsc::channel(),
+
             file_dialog_channel: mpsc::channel(),
-            stream: (stream, handle),
-            sink,
-            muted: false,
+            folder_dialog_channel: mpsc::channel(),
+            font_dialog_channel: mpsc::channel(),
+            audio: audio::default_backend(Tone::default()),
+            audio_recording: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            gif_recording: None,
+            recording_dialog_channel: mpsc::channel(),
+            #[cfg(not(target_arch = "wasm32"))]
+            movie: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            next_movie_frame: 0.0,
+            #[cfg(not(target_arch = "wasm32"))]
+            movie_keys_released: [false; 16],
+            #[cfg(not(target_arch = "wasm32"))]
+            movie_dialog_channel: mpsc::channel(),
+        }
+    }
+}
+
+impl Octarou {
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let mut app = Self {
+            settings: Settings::load(cc.storage),
+            ..Default::default()
+        };
+        app.audio.set_tone(app.settings.tone);
+        app.scan_library();
+        app.load_database();
+        app
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_args(mut self, args: Args, program: Option<Program>) -> Self {
+        // Flags given on the command line are not saved, and take precedence over whatever was
+        // remembered for the ROM.
+        self.overrides.mode = args
+            .mode
+            .map(|mode| Override::apply(&mut self.settings.mode, mode));
+        self.overrides.speed = args
+            .speed
+            .map(|speed| Override::apply(&mut self.settings.speed, speed));
+        let machine = args.machine(self.settings.machine.clone());
+        if machine != self.settings.machine {
+            self.overrides.machine = Some(Override::apply(&mut self.settings.machine, machine));
+        }
+        if args.mute {
+            self.overrides.muted = Some(Override::apply(&mut self.settings.muted, true));
+        }
+        if let Some(path) = args.record_audio {
+            self.start_audio_recording(path);
+        }
+
+        if let Some(program) = program {
+            self.file_dialog_channel.0.send(program).ok();
+        }
+        // The program is opened before the movie since the file channel is polled first.
+        if let Some(path) = args.replay {
+            self.movie_dialog_channel.0.send(path).ok();
         }
+        self
     }
 }
 
 impl eframe::App for Octarou {
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.stop_audio_recording();
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.stop_gif_recording();
+            self.stop_movie();
+        }
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        if let Some(path) = self.current_program.as_ref().and_then(|p| p.path.as_ref()) {
+            self.settings.remember_rom(path, &self.overrides);
+        }
+        self.settings.persisted(&self.overrides).save(storage);
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         self.screen_size = ctx.screen_rect().size();
 
-        let keys_down = (0..16)
-            .map(|key| ctx.input(|i| i.key_down(chip8_key_to_egui_key(key).unwrap())))
-            .collect::<Vec<bool>>()
-            .try_into()
-            .expect("Should never panic");
+        let mut keymap = self.settings.keymap.clone();
+        if let Some(metadata) = &self.metadata {
+            for &(egui_key, key) in &metadata.keys {
+                keymap.bind(key, egui_key);
+            }
+        }
+        let (mut keys_down, mut keys_released, time) =
+            ctx.input(|i| (keymap.keys_down(i), keymap.keys_released(i), i.time));
 
-        let keys_released = (0..16)
-            .map(|key| ctx.input(|i| i.key_released(chip8_key_to_egui_key(key).unwrap())))
-            .collect::<Vec<bool>>()
-            .try_into()
-            .expect("Should never panic");
+        let (touch_down, touch_released) = ctx.input(|i| self.touch_keypad.input(i));
+        for key in 0..16 {
+            keys_down[key] |= touch_down[key];
+            keys_released[key] |= touch_released[key];
+        }
 
         self.input(ctx);
 
-        if let Some(program) = self.file_dialog_channel.1.try_recv().ok() {
-            let filename = program.filename.clone();
-            self.current_program = Some(program);
-            ctx.send_viewport_cmd(egui::ViewportCommand::Title(format!(
-                "Octarou - {}",
-                filename
-            )));
-            self.load_interpreter();
+        if let Ok(program) = self.file_dialog_channel.1.try_recv() {
+            self.open_program(ctx, program);
         }
 
-        if let Some(interpreter) = &mut self.interpreter {
-            if interpreter.is_beeping() && !self.muted {
-                self.sink.play();
-            } else {
-                self.sink.pause();
-            }
+        if let Ok(path) = self.recording_dialog_channel.1.try_recv() {
+            self.start_audio_recording(path);
+        }
 
-            let result = interpreter.tick(&keys_down, &keys_released, self.speed);
+        if let Ok(directory) = self.folder_dialog_channel.1.try_recv() {
+            self.settings.library_directory = Some(directory);
+            self.scan_library();
+        }
 
-            if let Err(e) = result {
-                match e {
-                    InterpreterError::OutOfMemory => {
-                        error!("{}. Stopping execution.", e);
-                        self.interpreter = None;
-                    }
-                    _ => error!("{}.", e),
+        if let Ok(scan) = self.library_channel.1.try_recv() {
+            self.finish_library_scan(scan);
+        }
+
+        if let Ok(file) = self.font_dialog_channel.1.try_recv() {
+            self.load_font(file);
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Ok(path) = self.movie_dialog_channel.1.try_recv() {
+            self.play_movie(&path);
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let frame_due = self.movie_frame_due(time, &mut keys_released);
+        #[cfg(target_arch = "wasm32")]
+        let frame_due = true;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let (keys_down, keys_released) = match frame_due {
+            true => self.movie_keys(keys_down, keys_released),
+            false => (keys_down, keys_released),
+        };
+
+        if frame_due {
+            self.keypad.set_state(&keys_down, &keys_released, time);
+            self.keypad.advance(time);
+        }
+
+        let halted = self.post_mortem.is_some();
+        let mut ran_frame = false;
+        if let Some(interpreter) = self.interpreter.as_mut().filter(|_| !halted && frame_due) {
+            ran_frame = true;
+            // Movies run a fixed number of instructions per frame so that replays do not depend on
+            // how fast the host happens to be.
+            #[cfg(not(target_arch = "wasm32"))]
+            let result = match &self.movie {
+                Some(state) => {
+                    interpreter.run_frame(&mut self.keypad, state.movie().instructions_per_frame())
                 }
+                None => interpreter.tick(&mut self.keypad, self.settings.speed),
+            };
+            #[cfg(target_arch = "wasm32")]
+            let result = interpreter.tick(&mut self.keypad, self.settings.speed);
+
+            if let Err(e) = result {
+                self.fault(e);
+            }
+        }
+
+        let sound_timer = match self.settings.muted || self.post_mortem.is_some() {
+            true => 0,
+            false => self
+                .interpreter
+                .as_ref()
+                .map_or(0, |interpreter| interpreter.sound_timer()),
+        };
+        self.audio.update(sound_timer);
+        let sample = self
+            .interpreter
+            .as_ref()
+            .and_then(|interpreter| interpreter.sample());
+        match self.settings.muted || self.post_mortem.is_some() {
+            true => self.audio.set_sample(None),
+            false => self.audio.set_sample(sample),
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let (Some(recording), Some(interpreter)) = (&mut self.gif_recording, &self.interpreter) {
+            if let Err(e) = recording.add_frame(capture::Frame::new(interpreter.as_ref())) {
+                error!("{}. Stopping GIF recording.", e);
+                self.stop_gif_recording();
             }
         }
 
+        // Recordings take one frame of audio per emulated frame, however often egui updates.
+        if let Some(recording) = self.audio_recording.as_mut().filter(|_| ran_frame) {
+            let sound_timer = self
+                .interpreter
+                .as_ref()
+                .map_or(0, |interpreter| interpreter.sound_timer());
+            recording.capture.update(sound_timer);
+            recording.capture.set_sample(
+                self.interpreter
+                    .as_ref()
+                    .and_then(|interpreter| interpreter.sample()),
+            );
+        }
+
         self.ui(ctx);
         ctx.request_repaint();
     }
 }
 
 impl Octarou {
+    fn open_program(&mut self, ctx: &egui::Context, program: Program) {
+        if let Some(path) = self.current_program.as_ref().and_then(|p| p.path.as_ref()) {
+            self.settings.remember_rom(path, &self.overrides);
+        }
+        if self.current_program.is_some() {
+            self.overrides.clear_program(&mut self.settings);
+        }
+
+        let mut remembered = false;
+        if let Some(path) = &program.path {
+            if let Some(directory) = path.parent() {
+                self.settings.last_directory = Some(directory.to_path_buf());
+            }
+            self.settings.add_recent_file(path);
+            remembered = self.settings.restore_rom(path);
+        }
+
+        self.metadata = self
+            .database
+            .as_ref()
+            .and_then(|database| database.lookup(&program.hash));
+        if let Some(metadata) = &self.metadata {
+            info!(
+                "Recognised {} by {} ({}).",
+                metadata.title,
+                metadata.authors.join(", "),
+                metadata.platform
+            );
+            if metadata.mode.is_none() {
+                warn!(
+                    "{} requires the unsupported {} platform.",
+                    metadata.title, metadata.platform
+                );
+            }
+        }
+
+        // Settings remembered for the ROM or given on the command line win over the database,
+        // which wins over detection.
+        if let Some(speed) = self.metadata.as_ref().and_then(|metadata| metadata.speed) {
+            if !remembered {
+                self.settings.speed = speed;
+            }
+        }
+        match self.metadata.as_ref().and_then(|metadata| metadata.mode) {
+            Some(mode) if !remembered => self.settings.mode = mode,
+            _ if self.settings.auto_detect && !remembered && self.overrides.mode.is_none() => {
+                self.detect_mode(&program);
+            }
+            _ => (),
+        }
+        self.overrides.reapply_program(&mut self.settings);
+
+        ctx.send_viewport_cmd(egui::ViewportCommand::Title(format!(
+            "Octarou - {}",
+            program.filename
+        )));
+        self.current_program = Some(program);
+        self.resets = None;
+        self.load_interpreter();
+    }
+
+    fn start_audio_recording(&mut self, path: PathBuf) {
+        info!("Recording audio to {}.", path.display());
+        self.audio_recording = Some(AudioRecording {
+            path,
+            capture: CaptureBackend::new(self.settings.tone),
+        });
+    }
+
+    fn stop_audio_recording(&mut self) {
+        let Some(recording) = self.audio_recording.take() else {
+            return;
+        };
+
+        let result = std::fs::File::create(&recording.path).and_then(|file| {
+            audio::write_wav(std::io::BufWriter::new(file), recording.capture.samples())
+        });
+        match result {
+            Ok(()) => info!("Saved audio recording to {}.", recording.path.display()),
+            Err(e) => error!(
+                "Failed to save audio recording to {}: {}.",
+                recording.path.display(),
+                e
+            ),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn capture_path(&self, extension: &str) -> PathBuf {
+        let stem = self
+            .current_program
+            .as_ref()
+            .and_then(|program| {
+                std::path::Path::new(&program.filename)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+            })
+            .unwrap_or_else(|| "octarou".to_string());
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+
+        self.settings
+            .last_directory
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(format!("{}-{}.{}", stem, timestamp, extension))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn take_screenshot(&self) {
+        let Some(interpreter) = &self.interpreter else {
+            warn!("Nothing to take a screenshot of.");
+            return;
+        };
+
+        let path = self.capture_path("png");
+        let frame = capture::Frame::new(interpreter.as_ref());
+        let result = std::fs::File::create(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|file| {
+                capture::write_png(
+                    std::io::BufWriter::new(file),
+                    &frame,
+                    self.settings.capture_scale,
+                    self.palette(),
+                )
+            });
+
+        match result {
+            Ok(()) => info!("Saved screenshot to {}.", path.display()),
+            Err(e) => error!("Failed to save screenshot to {}: {}.", path.display(), e),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn toggle_gif_recording(&mut self) {
+        if self.gif_recording.is_some() {
+            self.stop_gif_recording();
+            return;
+        }
+
+        let Some(interpreter) = &self.interpreter else {
+            warn!("Nothing to record.");
+            return;
+        };
+
+        let path = self.capture_path("gif");
+        let frame = capture::Frame::new(interpreter.as_ref());
+        match GifRecorder::new(&path, &frame, self.settings.capture_scale, self.palette()) {
+            Ok(recording) => {
+                info!("Recording GIF to {}.", path.display());
+                self.gif_recording = Some(recording);
+            }
+            Err(e) => error!("Failed to record GIF to {}: {}.", path.display(), e),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn stop_gif_recording(&mut self) {
+        if let Some(recording) = self.gif_recording.take() {
+            match recording.finish() {
+                Ok(()) => info!("Saved GIF recording."),
+                Err(e) => error!("Failed to save GIF recording: {}.", e),
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start_movie_recording(&mut self) {
+        self.stop_movie();
+
+        let Some(program) = &self.current_program else {
+            warn!("Nothing to record.");
+            return;
+        };
+
+        // Recording restarts the program with a fresh seed so the movie covers the whole run.
+        let movie = Movie::new(
+            program,
+            self.settings.mode,
+            self.quirks(),
+            self.settings.machine.clone(),
+            self.settings.speed,
+            rand::random(),
+        );
+        match movie.interpreter(program) {
+            Ok(interpreter) => {
+                info!("Recording movie of {}.", program.filename);
+                self.interpreter = Some(interpreter);
+                self.keypad = Keypad::default();
+                self.post_mortem = None;
+                self.movie = Some(MovieState::Recording(movie));
+                self.next_movie_frame = 0.0;
+                self.movie_keys_released = [false; 16];
+            }
+            Err(e) => error!("{}.", e),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn play_movie(&mut self, path: &std::path::Path) {
+        self.stop_movie();
+
+        let Some(program) = &self.current_program else {
+            error!("Open the ROM the movie was recorded with before playing it.");
+            return;
+        };
+
+        let result = Movie::load(path).and_then(|movie| Ok((movie.interpreter(program)?, movie)));
+        match result {
+            Ok((interpreter, movie)) => {
+                info!(
+                    "Playing movie {} ({} frames).",
+                    path.display(),
+                    movie.frames.len()
+                );
+                self.interpreter = Some(interpreter);
+                self.keypad = Keypad::default();
+                self.post_mortem = None;
+                self.movie = Some(MovieState::Playing { movie, frame: 0 });
+                self.next_movie_frame = 0.0;
+                self.movie_keys_released = [false; 16];
+            }
+            Err(e) => error!("{}.", e),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn stop_movie(&mut self) {
+        match self.movie.take() {
+            Some(MovieState::Recording(movie)) => {
+                let path = self.capture_path("movie.json");
+                match movie.save(&path) {
+                    Ok(()) => info!("Saved movie to {}.", path.display()),
+                    Err(e) => error!("Failed to save movie to {}: {}.", path.display(), e),
+                }
+            }
+            Some(MovieState::Playing { .. }) => info!("Stopped movie playback."),
+            None => (),
+        }
+    }
+
+    // Movie frames run without any timing of their own, so they are paced against egui's clock,
+    // one frame at a time. Keys released while no frame is due are held back for the next one.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn movie_frame_due(&mut self, time: f64, keys_released: &mut [bool; 16]) -> bool {
+        if self.movie.is_none() {
+            return true;
+        }
+
+        for (pending, &released) in self
+            .movie_keys_released
+            .iter_mut()
+            .zip(keys_released.iter())
+        {
+            *pending |= released;
+        }
+        if time < self.next_movie_frame {
+            return false;
+        }
+
+        // A host that falls behind plays the movie slower rather than catching up in bursts.
+        self.next_movie_frame = (self.next_movie_frame + 1.0 / movie::FRAME_RATE as f64).max(time);
+        *keys_released = std::mem::take(&mut self.movie_keys_released);
+        true
+    }
+
+    // Records the live keypad state, or replaces it with the movie's while one is playing.
+    // Once a movie runs out, control returns to the keyboard.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn movie_keys(
+        &mut self,
+        keys_down: [bool; 16],
+        keys_released: [bool; 16],
+    ) -> ([bool; 16], [bool; 16]) {
+        match &mut self.movie {
+            Some(MovieState::Recording(movie)) => {
+                movie.record(&keys_down, &keys_released);
+                (keys_down, keys_released)
+            }
+            Some(MovieState::Playing { movie, frame }) => match movie.keys(*frame) {
+                Some(keys) => {
+                    *frame += 1;
+                    keys
+                }
+                None => {
+                    info!("Finished playing movie.");
+                    self.movie = None;
+                    (keys_down, keys_released)
+                }
+            },
+            None => (keys_down, keys_released),
+        }
+    }
+
+    fn detect_mode(&mut self, program: &Program) {
+        let detection = interpreter::detect(&program.data, &self.settings.machine);
+        let evidence = detection
+            .evidence
+            .iter()
+            .take(4)
+            .map(|(address, opcode, _)| format!("{:04X} at {:#05x}", opcode, address))
+            .collect::<Vec<String>>();
+
+        let reason = match detection.variant {
+            Variant::Chip8 => "no extended opcodes are reachable".to_string(),
+            Variant::HiresChip8 => format!(
+                "it starts with the hi-res CHIP-8 entry jump ({})",
+                evidence.join(", ")
+            ),
+            Variant::SuperChip => {
+                format!("it reaches SUPER-CHIP opcodes ({})", evidence.join(", "))
+            }
+            Variant::Chip8X => format!("it reaches CHIP-8X opcodes ({})", evidence.join(", ")),
+            Variant::MegaChip => {
+                format!("it reaches MEGA-CHIP opcodes ({})", evidence.join(", "))
+            }
+            Variant::XoChip => format!(
+                "it reaches XO-CHIP opcodes ({}), which are not supported",
+                evidence.join(", ")
+            ),
+        };
+        let mode = Mode::from(detection.variant);
+
+        info!(
+            "Selected {:?} mode for {} because {}.",
+            mode, program.filename, reason
+        );
+        self.settings.mode = mode;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_path(&mut self, path: &std::path::Path) {
+        match Program::from_path(path) {
+            Ok(program) => {
+                self.file_dialog_channel.0.send(program).ok();
+            }
+            Err(e) => error!("{}.", e),
+        }
+    }
+
+    // Large directories take a while to scan, so the scan runs in the background.
+    fn scan_library(&mut self) {
+        self.library = None;
+        if let Some(directory) = self.settings.library_directory.clone() {
+            let sender = self.library_channel.0.clone();
+            execute(async move {
+                let result = Library::scan(&directory);
+                sender.send(LibraryScan { directory, result }).ok();
+            });
+        }
+    }
+
+    fn finish_library_scan(&mut self, scan: LibraryScan) {
+        // The directory may have changed since the scan started.
+        if self.settings.library_directory.as_ref() != Some(&scan.directory) {
+            return;
+        }
+
+        match scan.result {
+            Ok(library) => {
+                info!(
+                    "Found {} ROMs in {}.",
+                    library.len(),
+                    library.root.display()
+                );
+                self.library = Some(library);
+            }
+            Err(e) => error!("Failed to scan {}: {}.", scan.directory.display(), e),
+        }
+    }
+
+    fn load_database(&mut self) {
+        self.database = None;
+        let Some(directory) = &self.settings.database_directory else {
+            return;
+        };
+
+        match Database::load(directory) {
+            Ok(database) => {
+                info!(
+                    "Loaded {} programs from {}.",
+                    database.len(),
+                    directory.display()
+                );
+                self.database = Some(database);
+            }
+            Err(e) => error!("{}.", e),
+        }
+    }
+
     fn load_interpreter(&mut self) {
+        // Restarting the interpreter would desynchronise any movie from its inputs.
+        #[cfg(not(target_arch = "wasm32"))]
+        self.stop_movie();
+
         if let Some(Program { ref data, .. }) = self.current_program {
-            self.interpreter = Some(match self.mode {
-                Mode::Chip8 => Box::new(Chip8::new(data)),
-                Mode::SuperChip => Box::new(Superchip::new(data)),
-            });
+            self.keypad = Keypad::default();
+            self.post_mortem = None;
+            self.interpreter = match self.settings.mode.interpreter(
+                data,
+                self.quirks(),
+                &self.settings.machine,
+                rand::random(),
+            ) {
+                Ok(interpreter) => Some(interpreter),
+                Err(e) => {
+                    error!("{}.", e);
+                    None
+                }
+            };
         }
     }
 
+    fn load_font(&mut self, file: FontFile) {
+        let font = &mut self.settings.machine.font;
+        let result = match file.slot {
+            FontSlot::Small => SmallFont::custom(file.data).map(|small| font.small = small),
+            FontSlot::Big => BigFont::custom(file.data).map(|big| font.big = big),
+        };
+
+        match result {
+            Ok(()) => self.load_interpreter(),
+            Err(e) => error!("Failed to load font: {}.", e),
+        }
+    }
+
+    fn fault(&mut self, e: InterpreterError) {
+        let policy = self.fault_policy;
+        match policy {
+            // There is no next instruction to skip to once the program counter has left memory.
+            FaultPolicy::Skip if e.kind != ErrorKind::OutOfMemory => {
+                error!("{}. Skipping the instruction.", e)
+            }
+            // Resetting cannot get past a fault that happens every time at the same address.
+            FaultPolicy::Reset if self.count_reset(e.address) <= MAX_RESETS => {
+                error!("{}. Resetting.", e);
+                self.load_interpreter();
+            }
+            _ => {
+                error!("{}. Halting.", e);
+                if let Some(interpreter) = &self.interpreter {
+                    self.post_mortem = Some(PostMortem::new(e, interpreter.as_ref()));
+                }
+            }
+        }
+    }
+
+    // Returns how many times in a row the program has been reset for a fault at `address`.
+    fn count_reset(&mut self, address: usize) -> usize {
+        match &mut self.resets {
+            Some((last_address, count)) if *last_address == address => *count += 1,
+            resets => *resets = Some((address, 1)),
+        }
+        self.resets.map_or(0, |(_, count)| count)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_post_mortem(&self) {
+        let Some(post_mortem) = &self.post_mortem else {
+            return;
+        };
+
+        let path = self.capture_path("dump.txt");
+        let result = std::fs::File::create(&path)
+            .and_then(|file| post_mortem.write(std::io::BufWriter::new(file)));
+        match result {
+            Ok(()) => info!("Saved post-mortem dump to {}.", path.display()),
+            Err(e) => error!(
+                "Failed to save post-mortem dump to {}: {}.",
+                path.display(),
+                e
+            ),
+        }
+    }
+
+    fn quirks(&self) -> Quirks {
+        // Quirks from the database only apply to the platform they were listed for.
+        self.metadata
+            .as_ref()
+            .filter(|metadata| metadata.mode == Some(self.settings.mode))
+            .map_or_else(
+                || self.settings.mode.default_quirks(),
+                |metadata| metadata.quirks,
+            )
+    }
+
+    fn palette(&self) -> Palette {
+        self.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.palette)
+            .unwrap_or(self.settings.palette)
+    }
+
     fn input(&mut self, ctx: &egui::Context) {
+        let wants_keyboard_input = ctx.wants_keyboard_input();
+
         ctx.input_mut(|i| {
+            for file in i.raw.dropped_files.drain(..) {
+                match Program::from_dropped_file(&file) {
+                    Ok(program) => {
+                        self.file_dialog_channel.0.send(program).ok();
+                    }
+                    Err(e) => error!("{}.", e),
+                }
+            }
+
+            if !wants_keyboard_input {
+                for event in &i.events {
+                    if let egui::Event::Paste(text) = event {
+                        match Program::from_hex(text) {
+                            Ok(program) => {
+                                self.file_dialog_channel.0.send(program).ok();
+                            }
+                            Err(e) => error!("{}.", e),
+                        }
+                    }
+                }
+            }
+
+            if let Some(key) = self.rebinding {
+                let pressed = i.events.iter().find_map(|event| match event {
+                    egui::Event::Key {
+                        key, pressed: true, ..
+                    } => Some(*key),
+                    _ => None,
+                });
+
+                match pressed {
+                    Some(egui::Key::Escape) => self.rebinding = None,
+                    Some(egui_key) => {
+                        self.settings.keymap.bind(key, egui_key);
+                        self.rebinding = None;
+                    }
+                    None => (),
+                }
+                return;
+            }
+
             if i.consume_shortcut(&egui::KeyboardShortcut::new(
                 egui::Modifiers::CTRL,
                 egui::Key::O,
@@ -149,12 +1080,97 @@ impl Octarou {
             if i.consume_key(egui::Modifiers::CTRL, egui::Key::R) {
                 self.load_interpreter();
             }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            if i.consume_key(egui::Modifiers::NONE, egui::Key::F12) {
+                self.take_screenshot();
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            if i.consume_key(egui::Modifiers::NONE, egui::Key::F9) {
+                self.toggle_gif_recording();
+            }
         });
     }
 
     fn ui(&mut self, ctx: &egui::Context) {
         self.side_panel(ctx);
+        self.touch_keypad_panel(ctx);
         self.central_panel(ctx);
+        self.post_mortem_window(ctx);
+        self.drop_hint(ctx);
+    }
+
+    fn drop_hint(&self, ctx: &egui::Context) {
+        if ctx.input(|i| i.raw.hovered_files.is_empty()) {
+            return;
+        }
+
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("drop-hint"),
+        ));
+        let rect = ctx.screen_rect();
+        painter.rect_filled(rect, 0.0, egui::Color32::from_black_alpha(192));
+        painter.text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            "Drop ROM to load",
+            egui::TextStyle::Heading.resolve(&ctx.style()),
+            egui::Color32::WHITE,
+        );
+    }
+
+    fn post_mortem_window(&mut self, ctx: &egui::Context) {
+        let Some(post_mortem) = &self.post_mortem else {
+            return;
+        };
+
+        let mut reset = false;
+        let mut stop = false;
+        egui::Window::new("\u{1F480} Post-mortem")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                post_mortem.ui(ui);
+                ui.separator();
+                ui.horizontal(|ui| {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if ui.button("\u{1F4BE} Save dump").clicked() {
+                        self.save_post_mortem();
+                    }
+                    reset = ui.button("\u{1F504} Reset").clicked();
+                    stop = ui.button("\u{23F9} Stop").clicked();
+                });
+            });
+
+        if reset {
+            self.load_interpreter();
+        } else if stop {
+            self.post_mortem = None;
+            self.interpreter = None;
+        }
+    }
+
+    fn touch_keypad_panel(&mut self, ctx: &egui::Context) {
+        if !self
+            .settings
+            .touch_keypad
+            .is_visible(self.touch_keypad.touch_seen())
+        {
+            self.touch_keypad.hide();
+            return;
+        }
+
+        egui::TopBottomPanel::bottom("touch-keypad")
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.add_space(6.0);
+                    self.touch_keypad.ui(ui, &self.settings.keymap);
+                    ui.add_space(6.0);
+                });
+            });
     }
 
     fn side_panel(&mut self, ctx: &egui::Context) {
@@ -174,28 +1190,389 @@ impl Octarou {
             .striped(false)
             .show(ui, |ui| {
                 ui.label("Speed:");
-                ui.add(egui::Slider::new(&mut self.speed, 100..=2000));
+                ui.add(egui::Slider::new(&mut self.settings.speed, 100..=2000));
                 ui.end_row();
 
                 ui.label("Mode:");
                 egui::ComboBox::from_id_source("mode-selector")
-                    .selected_text(format!("{:?}", self.mode))
+                    .selected_text(format!("{:?}", self.settings.mode))
                     .show_ui(ui, |ui| {
-                        let chip8 = ui.selectable_value(&mut self.mode, Mode::Chip8, "Chip8");
-                        let superchip =
-                            ui.selectable_value(&mut self.mode, Mode::SuperChip, "SuperChip");
+                        for mode in Mode::ALL {
+                            if ui
+                                .selectable_value(
+                                    &mut self.settings.mode,
+                                    mode,
+                                    format!("{:?}", mode),
+                                )
+                                .clicked()
+                            {
+                                self.load_interpreter();
+                            }
+                        }
+                    });
 
-                        if chip8.clicked() || superchip.clicked() {
-                            self.load_interpreter();
+                ui.end_row();
+
+                ui.label("On fault:");
+                egui::ComboBox::from_id_source("fault-policy-selector")
+                    .selected_text(format!("{:?}", self.fault_policy))
+                    .show_ui(ui, |ui| {
+                        for policy in FaultPolicy::ALL {
+                            ui.selectable_value(
+                                &mut self.fault_policy,
+                                policy,
+                                format!("{:?}", policy),
+                            );
                         }
                     });
+                ui.end_row();
 
+                ui.label("Auto-detect mode:");
+                ui.checkbox(&mut self.settings.auto_detect, "")
+                    .on_hover_text("Pick the mode of unknown ROMs from the opcodes they use");
+                ui.end_row();
+
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    ui.label("Program database:");
+                    let mut directory = self
+                        .settings
+                        .database_directory
+                        .as_ref()
+                        .map(|d| d.display().to_string())
+                        .unwrap_or_default();
+                    let response = ui
+                        .add(
+                            egui::TextEdit::singleline(&mut directory)
+                                .hint_text("folder with programs.json"),
+                        )
+                        .on_hover_text(
+                            "The database folder of the CHIP-8 program database, used to pick \
+                             the mode, quirks, speed, colours and keys of known ROMs",
+                        );
+                    if response.changed() {
+                        self.settings.database_directory =
+                            (!directory.is_empty()).then(|| PathBuf::from(directory));
+                    }
+                    if response.lost_focus() {
+                        self.load_database();
+                    }
+                    ui.end_row();
+                }
+
+                ui.label("Touch keypad:");
+                egui::ComboBox::from_id_source("touch-keypad-selector")
+                    .selected_text(format!("{:?}", self.settings.touch_keypad))
+                    .show_ui(ui, |ui| {
+                        for visibility in Visibility::ALL {
+                            ui.selectable_value(
+                                &mut self.settings.touch_keypad,
+                                visibility,
+                                format!("{:?}", visibility),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text("Auto shows the keypad once a touch screen is used");
                 ui.end_row();
 
                 ui.label("Mute audio:");
-                ui.checkbox(&mut self.muted, "");
+                ui.checkbox(&mut self.settings.muted, "");
+                ui.end_row();
+
+                let tone = &mut self.settings.tone;
+                let mut changed = false;
+
+                ui.label("Waveform:");
+                egui::ComboBox::from_id_source("waveform-selector")
+                    .selected_text(format!("{:?}", tone.waveform))
+                    .show_ui(ui, |ui| {
+                        for waveform in Waveform::ALL {
+                            changed |= ui
+                                .selectable_value(
+                                    &mut tone.waveform,
+                                    waveform,
+                                    format!("{:?}", waveform),
+                                )
+                                .changed();
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Frequency:");
+                changed |= ui
+                    .add(
+                        egui::Slider::new(&mut tone.frequency, 50.0..=2000.0)
+                            .logarithmic(true)
+                            .suffix(" Hz"),
+                    )
+                    .changed();
+                ui.end_row();
+
+                ui.label("Volume:");
+                changed |= ui
+                    .add(egui::Slider::new(&mut tone.volume, 0.0..=1.0))
+                    .changed();
+                ui.end_row();
+
+                ui.label("Attack:");
+                changed |= ui
+                    .add(egui::Slider::new(&mut tone.attack_ms, 0.0..=50.0).suffix(" ms"))
+                    .changed();
+                ui.end_row();
+
+                ui.label("Release:");
+                changed |= ui
+                    .add(egui::Slider::new(&mut tone.release_ms, 0.0..=50.0).suffix(" ms"))
+                    .changed();
+                ui.end_row();
+
+                if changed {
+                    self.audio.set_tone(self.settings.tone);
+                }
+
+                ui.label("Foreground:");
+                ui.color_edit_button_srgba(&mut self.settings.palette.foreground);
+                ui.end_row();
+
+                ui.label("Background:");
+                ui.color_edit_button_srgba(&mut self.settings.palette.background);
+                ui.end_row();
+
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    ui.label("Capture scale:");
+                    ui.add(egui::Slider::new(&mut self.settings.capture_scale, 1..=16).suffix("x"));
+                    ui.end_row();
+                }
+            });
+
+        ui.vertical_centered_justified(|ui| {
+            if ui.button("Reset to defaults").clicked() {
+                self.settings = Settings::default();
+                self.overrides = Overrides::default();
+                self.audio.set_tone(self.settings.tone);
+                self.rebinding = None;
+                ctx.memory_mut(|mem| *mem = Default::default());
+            }
+        });
+
+        ui.separator();
+        egui::CollapsingHeader::new("Machine").show(ui, |ui| self.machine_editor(ui));
+        egui::CollapsingHeader::new("Keymap").show(ui, |ui| self.keymap_editor(ui));
+        egui::CollapsingHeader::new("Keypad state").show(ui, |ui| self.keypad_state(ui));
+    }
+
+    fn machine_editor(&mut self, ui: &mut egui::Ui) {
+        let machine = &mut self.settings.machine;
+        let mut changed = false;
+        let mut load_font = None;
+
+        egui::Grid::new("machine")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Memory size:");
+                changed |= override_value(ui, &mut machine.memory_size, 0x1000, |ui, size| {
+                    ui.add(
+                        egui::DragValue::new(size)
+                            .clamp_range(0x200..=MAX_MEMORY_SIZE)
+                            .suffix(" B"),
+                    )
+                });
+                ui.end_row();
+
+                ui.label("Program address:");
+                changed |= override_value(ui, &mut machine.program_address, 0x200, hex_value);
+                ui.end_row();
+
+                ui.label("Font address:");
+                changed |= override_value(ui, &mut machine.font_address, 0x50, hex_value);
+                ui.end_row();
+
+                ui.label("Font:");
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("small-font-selector")
+                        .selected_text(machine.font.small.name())
+                        .show_ui(ui, |ui| {
+                            for font in SmallFont::BUILT_IN {
+                                let name = font.name();
+                                changed |= ui
+                                    .selectable_value(&mut machine.font.small, font, name)
+                                    .changed();
+                            }
+                        });
+                    if ui
+                        .button("Load...")
+                        .on_hover_text("Load 16 glyphs of 5 bytes each from a file")
+                        .clicked()
+                    {
+                        load_font = Some(FontSlot::Small);
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Big font:");
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("big-font-selector")
+                        .selected_text(machine.font.big.name())
+                        .show_ui(ui, |ui| {
+                            for font in BigFont::BUILT_IN {
+                                let name = font.name();
+                                changed |= ui
+                                    .selectable_value(&mut machine.font.big, font, name)
+                                    .changed();
+                            }
+                        });
+                    if ui
+                        .button("Load...")
+                        .on_hover_text("Load up to 16 glyphs of 10 bytes each from a file")
+                        .clicked()
+                    {
+                        load_font = Some(FontSlot::Big);
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Display size:");
+                ui.horizontal(|ui| {
+                    let mut custom = machine.display_size.is_some();
+                    if ui
+                        .checkbox(&mut custom, "")
+                        .on_hover_text(
+                            "Override the variant's default of 64x32 (CHIP-8), 128x64 (SUPER-CHIP) \
+                             or 64x64 (hi-res CHIP-8)",
+                        )
+                        .changed()
+                    {
+                        machine.display_size = custom.then_some((64, 32));
+                        changed = true;
+                    }
+                    if let Some((width, height)) = &mut machine.display_size {
+                        changed |= ui
+                            .add(egui::DragValue::new(width).clamp_range(8..=256))
+                            .changed();
+                        ui.label("x");
+                        changed |= ui
+                            .add(egui::DragValue::new(height).clamp_range(8..=256))
+                            .changed();
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Stack depth:");
+                changed |= override_value(ui, &mut machine.stack.depth, 16, |ui, depth| {
+                    ui.add(egui::DragValue::new(depth).clamp_range(1..=64))
+                });
+                ui.end_row();
+
+                ui.label("Stack in memory:");
+                changed |= ui
+                    .checkbox(&mut machine.stack.in_memory, "")
+                    .on_hover_text(
+                        "Keep the stack in emulated memory below 0xED0, like the COSMAC VIP",
+                    )
+                    .changed();
                 ui.end_row();
             });
+
+        ui.vertical_centered_justified(|ui| {
+            if ui.button("Reset machine").clicked() {
+                *machine = MachineConfig::default();
+                changed = true;
+            }
+        });
+
+        if let Some(slot) = load_font {
+            self.open_font_dialog(slot);
+        }
+        if changed {
+            self.load_interpreter();
+        }
+    }
+
+    // Shows the keypad as the interpreter sees it, to tell input that never arrived apart from
+    // input the ROM ignores.
+    fn keypad_state(&self, ui: &mut egui::Ui) {
+        let palette = self.palette();
+        egui::Grid::new("keypad-state")
+            .spacing([2.0, 2.0])
+            .show(ui, |ui| {
+                for row in VIP_LAYOUT {
+                    for key in row {
+                        let (fill, text) = match self.keypad.is_down(key) == Some(true) {
+                            true => (palette.foreground, palette.background),
+                            false => (palette.background, palette.foreground),
+                        };
+                        let (rect, _) =
+                            ui.allocate_exact_size(egui::Vec2::splat(24.0), egui::Sense::hover());
+                        ui.painter().rect_filled(rect, 2.0, fill);
+                        ui.painter().text(
+                            rect.center(),
+                            egui::Align2::CENTER_CENTER,
+                            format!("{:X}", key),
+                            egui::TextStyle::Monospace.resolve(ui.style()),
+                            text,
+                        );
+                    }
+                    ui.end_row();
+                }
+            });
+
+        if self.interpreter.is_some() && self.keypad.is_waiting() {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                "\u{23F3} Waiting for a key press (FX0A)",
+            );
+        }
+    }
+
+    fn keymap_editor(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Preset:");
+            ui.menu_button("Load", |ui| {
+                for preset in Preset::ALL {
+                    if ui.button(format!("{:?}", preset)).clicked() {
+                        self.settings.keymap = Keymap::from_preset(preset);
+                        self.rebinding = None;
+                        ui.close_menu();
+                    }
+                }
+            });
+        });
+
+        egui::Grid::new("keymap")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                for key in 0..16 {
+                    ui.monospace(format!("{:X}", key));
+                    ui.horizontal_wrapped(|ui| {
+                        for egui_key in self.settings.keymap.bindings(key).to_vec() {
+                            if ui
+                                .small_button(egui_key.symbol_or_name())
+                                .on_hover_text("Click to unbind")
+                                .clicked()
+                            {
+                                self.settings.keymap.unbind(key, egui_key);
+                            }
+                        }
+
+                        if self.rebinding == Some(key) {
+                            if ui
+                                .small_button("Press a key...")
+                                .on_hover_text("Press Escape to cancel")
+                                .clicked()
+                            {
+                                self.rebinding = None;
+                            }
+                        } else if ui.small_button("+").clicked() {
+                            self.rebinding = Some(key);
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
     }
 
     fn menu(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
@@ -210,6 +1587,119 @@ impl Octarou {
                 ui.close_menu();
             }
 
+            #[cfg(not(target_arch = "wasm32"))]
+            ui.add_enabled_ui(!self.settings.recent_files.is_empty(), |ui| {
+                ui.menu_button("Recent", |ui| {
+                    for path in self.settings.recent_files.clone() {
+                        let filename = path
+                            .file_name()
+                            .map(|f| f.to_string_lossy().to_string())
+                            .unwrap_or_default();
+
+                        if ui
+                            .button(filename)
+                            .on_hover_text(path.display().to_string())
+                            .clicked()
+                        {
+                            self.open_path(&path);
+                            ui.close_menu();
+                        }
+                    }
+
+                    ui.separator();
+
+                    if ui.button("Clear").clicked() {
+                        self.settings.recent_files.clear();
+                        ui.close_menu();
+                    }
+                });
+            });
+
+            ui.separator();
+
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                if ui
+                    .add(egui::Button::new("\u{1F4F7} Screenshot").shortcut_text(
+                        ctx.format_shortcut(&egui::KeyboardShortcut::new(
+                            egui::Modifiers::NONE,
+                            egui::Key::F12,
+                        )),
+                    ))
+                    .clicked()
+                {
+                    self.take_screenshot();
+                    ui.close_menu();
+                }
+
+                let label = match self.gif_recording.is_some() {
+                    true => "\u{23F9} Stop GIF recording",
+                    false => "\u{23FA} Record GIF",
+                };
+                if ui
+                    .add(egui::Button::new(label).shortcut_text(ctx.format_shortcut(
+                        &egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::F9),
+                    )))
+                    .clicked()
+                {
+                    self.toggle_gif_recording();
+                    ui.close_menu();
+                }
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            if self.audio_recording.is_some() {
+                if ui.button("\u{23F9} Stop audio recording").clicked() {
+                    self.stop_audio_recording();
+                    ui.close_menu();
+                }
+            } else if ui.button("\u{23FA} Record audio...").clicked() {
+                self.open_recording_dialog();
+                ui.close_menu();
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                ui.separator();
+
+                match self.movie {
+                    Some(MovieState::Recording(_)) => {
+                        if ui.button("\u{23F9} Stop movie recording").clicked() {
+                            self.stop_movie();
+                            ui.close_menu();
+                        }
+                    }
+                    Some(MovieState::Playing { .. }) => {
+                        if ui.button("\u{23F9} Stop movie playback").clicked() {
+                            self.stop_movie();
+                            ui.close_menu();
+                        }
+                    }
+                    None => {
+                        if ui
+                            .add_enabled(
+                                self.current_program.is_some(),
+                                egui::Button::new("\u{23FA} Record movie"),
+                            )
+                            .clicked()
+                        {
+                            self.start_movie_recording();
+                            ui.close_menu();
+                        }
+                        if ui
+                            .add_enabled(
+                                self.current_program.is_some(),
+                                egui::Button::new("\u{25B6} Play movie..."),
+                            )
+                            .clicked()
+                        {
+                            self.open_movie_dialog();
+                            ui.close_menu();
+                        }
+                    }
+                }
+            }
+
             ui.separator();
 
             if ui.button("Quit").clicked() {
@@ -226,6 +1716,11 @@ impl Octarou {
                     Tab::Controls,
                     egui::RichText::new("\u{1F5A5} Display").heading(),
                 );
+                ui.selectable_value(
+                    &mut self.current_tab,
+                    Tab::Library,
+                    egui::RichText::new("\u{1F4DA} Library").heading(),
+                );
                 ui.selectable_value(
                     &mut self.current_tab,
                     Tab::Logs,
@@ -236,35 +1731,183 @@ impl Octarou {
             ui.separator();
             match self.current_tab {
                 Tab::Controls => self.interpreter_display(ui),
+                Tab::Library => self.library_browser(ui),
                 Tab::Logs => egui_logger::logger_ui(ui),
             }
         });
     }
 
-    fn interpreter_display(&mut self, ui: &mut egui::Ui) {
-        egui::Frame::dark_canvas(ui.style()).show(ui, |ui| {
-            let rect = ui.available_rect_before_wrap();
-            let (response, painter) = ui.allocate_painter(
-                if rect.aspect_ratio() < 2.0 {
-                    egui::vec2(rect.size().x, 0.5 * rect.size().x)
-                } else {
-                    egui::vec2(2.0 * rect.size().y, rect.size().y)
-                },
-                egui::Sense::focusable_noninteractive(),
+    #[cfg(target_arch = "wasm32")]
+    fn library_browser(&mut self, ui: &mut egui::Ui) {
+        ui.label("The ROM library is not available in the web version.");
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn library_browser(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Directory:");
+
+            let mut directory = self
+                .settings
+                .library_directory
+                .as_ref()
+                .map(|d| d.display().to_string())
+                .unwrap_or_default();
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut directory).hint_text("e.g. the roms submodule"),
             );
-            self.paint_grid(&painter, response.rect);
+            if response.changed() {
+                self.settings.library_directory =
+                    (!directory.is_empty()).then(|| PathBuf::from(directory));
+            }
+            if response.lost_focus() {
+                self.scan_library();
+            }
+
+            if ui.button("Browse...").clicked() {
+                self.open_folder_dialog();
+            }
+            if ui.button("\u{1F504} Rescan").clicked() {
+                self.scan_library();
+            }
         });
+
+        ui.separator();
+
+        let Some(library) = &self.library else {
+            ui.label("Choose a directory to browse its ROMs.");
+            return;
+        };
+
+        if library.is_empty() {
+            ui.label("No ROMs found.");
+            return;
+        }
+
+        let mut launch = None;
+        egui::SidePanel::right("library-details")
+            .resizable(true)
+            .show_inside(ui, |ui| {
+                let entry = library
+                    .groups
+                    .values()
+                    .flatten()
+                    .find(|entry| Some(&entry.path) == self.library_selection.as_ref());
+
+                match entry {
+                    Some(entry) => {
+                        ui.heading(&entry.name);
+                        if ui.button("\u{25B6} Launch").clicked() {
+                            launch = Some(entry.path.clone());
+                        }
+                        ui.separator();
+                        egui::ScrollArea::vertical()
+                            .id_source("library-description")
+                            .show(ui, |ui| match &entry.description {
+                                Some(description) => ui.monospace(description),
+                                None => ui.weak("No description available."),
+                            });
+                    }
+                    None => {
+                        ui.weak("Select a ROM to see its description.");
+                    }
+                }
+            });
+
+        egui::ScrollArea::vertical()
+            .id_source("library-entries")
+            .show(ui, |ui| {
+                for (group, entries) in &library.groups {
+                    let title = match group.as_os_str().is_empty() {
+                        true => library.root.display().to_string(),
+                        false => group.display().to_string(),
+                    };
+
+                    egui::CollapsingHeader::new(title)
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            for entry in entries {
+                                let selected = Some(&entry.path) == self.library_selection.as_ref();
+                                let response = ui.selectable_label(selected, &entry.name);
+                                if response.clicked() {
+                                    self.library_selection = Some(entry.path.clone());
+                                }
+                                if response.double_clicked() {
+                                    launch = Some(entry.path.clone());
+                                }
+                            }
+                        });
+                }
+            });
+
+        if let Some(path) = launch {
+            self.open_path(&path);
+            self.current_tab = Tab::Controls;
+        }
+    }
+
+    fn interpreter_display(&mut self, ui: &mut egui::Ui) {
+        egui::Frame::dark_canvas(ui.style())
+            .fill(self.palette().background)
+            .show(ui, |ui| {
+                let rect = ui.available_rect_before_wrap();
+                let aspect_ratio = self.interpreter.as_ref().map_or(2.0, |interpreter| {
+                    let display = interpreter.display();
+                    display[0].len() as f32 / display.len() as f32
+                });
+                let (response, painter) = ui.allocate_painter(
+                    if rect.aspect_ratio() < aspect_ratio {
+                        egui::vec2(rect.size().x, rect.size().x / aspect_ratio)
+                    } else {
+                        egui::vec2(aspect_ratio * rect.size().y, rect.size().y)
+                    },
+                    egui::Sense::focusable_noninteractive(),
+                );
+                self.paint_grid(&painter, response.rect);
+            });
     }
 
-    fn paint_grid(&self, painter: &egui::Painter, rect: egui::Rect) {
+    fn paint_grid(&mut self, painter: &egui::Painter, rect: egui::Rect) {
+        let palette = self.palette();
         if let Some(interpreter) = &self.interpreter {
+            // True colour displays are too large to draw pixel by pixel.
+            if let Some(rows) = interpreter.rgb_display() {
+                let size = [rows[0].len() / 3, rows.len()];
+                let image = egui::ColorImage::from_rgb(size, &rows.concat());
+                let texture = match &mut self.screen_texture {
+                    Some(texture) => {
+                        texture.set(image, egui::TextureOptions::NEAREST);
+                        texture
+                    }
+                    None => self.screen_texture.insert(painter.ctx().load_texture(
+                        "screen",
+                        image,
+                        egui::TextureOptions::NEAREST,
+                    )),
+                };
+                painter.image(
+                    texture.id(),
+                    rect,
+                    egui::Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(1.0, 1.0)),
+                    egui::Color32::WHITE,
+                );
+                return;
+            }
+
             let scale = egui::vec2(
                 rect.size().x / interpreter.display()[0].len() as f32,
                 rect.size().y / interpreter.display().len() as f32,
             );
+            let colors = interpreter.colors();
             for (y, row) in interpreter.display().iter().enumerate() {
                 for (x, &cell) in row.iter().enumerate() {
-                    if cell == 1u8 {
+                    let color = match colors {
+                        Some(colors) => colors
+                            .get(cell as usize)
+                            .map(|&[r, g, b]| egui::Color32::from_rgb(r, g, b)),
+                        None => (cell == 1u8).then_some(palette.foreground),
+                    };
+                    if let Some(color) = color {
                         let points = [
                             painter.round_pos_to_pixels(
                                 rect.min + egui::Vec2::new(x as f32 * scale.x, y as f32 * scale.y),
@@ -280,7 +1923,7 @@ impl Octarou {
                         painter.rect_filled(
                             egui::Rect::from_points(&points),
                             egui::Rounding::ZERO,
-                            egui::Color32::WHITE,
+                            color,
                         );
                     }
                 }
@@ -289,9 +1932,15 @@ impl Octarou {
     }
 
     fn open_file_dialog(&mut self) {
+        let directory = self
+            .settings
+            .last_directory
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("/"));
+
         let task = rfd::AsyncFileDialog::new()
-            .add_filter("chip8", &["ch8"])
-            .set_directory("/")
+            .add_filter("chip8", &["ch8", "sc8", "xo8", "c8", "mc8"])
+            .set_directory(directory)
             .pick_file();
 
         let sender = self.file_dialog_channel.0.clone();
@@ -303,26 +1952,11 @@ impl Octarou {
 
             if let Some(file) = file {
                 #[cfg(not(target_arch = "wasm32"))]
-                {
-                    let filename = file
-                        .path()
-                        .file_name()
-                        .map(|f| f.to_string_lossy().to_string())
-                        .unwrap_or("".to_string());
-
-                    let mut data = Vec::new();
-                    let result = File::open(file.path()).map(|mut f| f.read_to_end(&mut data));
-                    if let Err(e) = result {
-                        error!("{}.", e);
-                    }
-
-                    let program = Program::new(filename, &data);
-                    match program {
-                        Ok(program) => {
-                            sender.send(program).ok();
-                        }
-                        Err(e) => error!("{},", e),
+                match Program::from_path(file.path()) {
+                    Ok(program) => {
+                        sender.send(program).ok();
                     }
+                    Err(e) => error!("{}.", e),
                 }
 
                 #[cfg(target_arch = "wasm32")]
@@ -341,28 +1975,106 @@ impl Octarou {
             }
         });
     }
-}
 
-fn chip8_key_to_egui_key(key: u8) -> Option<egui::Key> {
-    match key {
-        0 => Some(egui::Key::X),
-        1 => Some(egui::Key::Num1),
-        2 => Some(egui::Key::Num2),
-        3 => Some(egui::Key::Num3),
-        4 => Some(egui::Key::Q),
-        5 => Some(egui::Key::W),
-        6 => Some(egui::Key::E),
-        7 => Some(egui::Key::A),
-        8 => Some(egui::Key::S),
-        9 => Some(egui::Key::D),
-        10 => Some(egui::Key::Z),
-        11 => Some(egui::Key::C),
-        12 => Some(egui::Key::Num4),
-        13 => Some(egui::Key::R),
-        14 => Some(egui::Key::F),
-        15 => Some(egui::Key::V),
-        _ => None,
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_recording_dialog(&mut self) {
+        let mut dialog = rfd::AsyncFileDialog::new()
+            .add_filter("wav", &["wav"])
+            .set_file_name("recording.wav");
+        if let Some(directory) = &self.settings.last_directory {
+            dialog = dialog.set_directory(directory);
+        }
+        let task = dialog.save_file();
+        let sender = self.recording_dialog_channel.0.clone();
+
+        execute(async move {
+            if let Some(file) = task.await {
+                sender.send(file.path().to_path_buf()).ok();
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_movie_dialog(&mut self) {
+        let mut dialog = rfd::AsyncFileDialog::new().add_filter("movie", &["json"]);
+        if let Some(directory) = &self.settings.last_directory {
+            dialog = dialog.set_directory(directory);
+        }
+        let task = dialog.pick_file();
+        let sender = self.movie_dialog_channel.0.clone();
+
+        execute(async move {
+            if let Some(file) = task.await {
+                sender.send(file.path().to_path_buf()).ok();
+            }
+        });
+    }
+
+    fn open_font_dialog(&mut self, slot: FontSlot) {
+        let mut dialog = rfd::AsyncFileDialog::new();
+        if let Some(directory) = &self.settings.last_directory {
+            dialog = dialog.set_directory(directory);
+        }
+        let task = dialog.pick_file();
+        let sender = self.font_dialog_channel.0.clone();
+
+        execute(async move {
+            if let Some(file) = task.await {
+                let data = file.read().await;
+                sender.send(FontFile { slot, data }).ok();
+            }
+        });
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_folder_dialog(&mut self) {
+        let mut dialog = rfd::AsyncFileDialog::new();
+        if let Some(directory) = &self.settings.library_directory {
+            dialog = dialog.set_directory(directory);
+        }
+        let task = dialog.pick_folder();
+        let sender = self.folder_dialog_channel.0.clone();
+
+        execute(async move {
+            if let Some(folder) = task.await {
+                sender.send(folder.path().to_path_buf()).ok();
+            }
+        });
+    }
+}
+
+// A checkbox that overrides the variant's default, followed by an editor for the value while it
+// is overridden. Returns whether the value changed.
+fn override_value(
+    ui: &mut egui::Ui,
+    value: &mut Option<usize>,
+    initial: usize,
+    editor: impl FnOnce(&mut egui::Ui, &mut usize) -> egui::Response,
+) -> bool {
+    ui.horizontal(|ui| {
+        let mut custom = value.is_some();
+        let mut changed = ui
+            .checkbox(&mut custom, "")
+            .on_hover_text("Override the variant's default")
+            .changed();
+        if changed {
+            *value = custom.then_some(initial);
+        }
+        if let Some(value) = value {
+            changed |= editor(ui, value).changed();
+        }
+        changed
+    })
+    .inner
+}
+
+fn hex_value(ui: &mut egui::Ui, address: &mut usize) -> egui::Response {
+    ui.add(
+        egui::DragValue::new(address)
+            .clamp_range(0..=MAX_MEMORY_SIZE - 1)
+            .hexadecimal(4, false, true)
+            .prefix("0x"),
+    )
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -376,3 +2088,28 @@ fn execute<F: std::future::Future<Output = ()> + Send + 'static>(f: F) {
 fn execute<F: std::future::Future<Output = ()> + 'static>(f: F) {
     wasm_bindgen_futures::spawn_local(f);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_hex_reads_bytes() {
+        let program = Program::from_hex("00E0 a2 1E\n6001").unwrap();
+        assert_eq!(program.data(), [0x00, 0xE0, 0xA2, 0x1E, 0x60, 0x01]);
+        assert_eq!(program.filename(), "clipboard");
+    }
+
+    #[test]
+    fn from_hex_accepts_prefixes_and_commas() {
+        let program = Program::from_hex("0x00, 0xE0,0X12 0x00").unwrap();
+        assert_eq!(program.data(), [0x00, 0xE0, 0x12, 0x00]);
+    }
+
+    #[test]
+    fn from_hex_rejects_invalid_text() {
+        assert!(Program::from_hex("00E0 zz").is_err());
+        assert!(Program::from_hex("00E").is_err());
+        assert!(Program::from_hex(" \n").is_err());
+    }
+}
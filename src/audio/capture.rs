@@ -0,0 +1,44 @@
+use super::{AudioBackend, SamplePlayer, Tone, ToneGenerator, FRAME_RATE, SAMPLE_RATE};
+use crate::interpreter::Sample;
+
+// Renders the buzzer and digitised sounds into an in-memory buffer instead of playing it, one
+// frame at a time.
+#[derive(Debug, Clone)]
+pub struct CaptureBackend {
+    generator: ToneGenerator,
+    player: SamplePlayer,
+    samples: Vec<f32>,
+}
+
+impl CaptureBackend {
+    pub fn new(tone: Tone) -> Self {
+        Self {
+            generator: ToneGenerator::new(tone, SAMPLE_RATE),
+            player: SamplePlayer::new(tone.volume, SAMPLE_RATE),
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+}
+
+impl AudioBackend for CaptureBackend {
+    fn update(&mut self, sound_timer: u8) {
+        self.generator.set_sound_timer(sound_timer);
+        for _ in 0..SAMPLE_RATE / FRAME_RATE {
+            let sample = self.generator.next_sample() + self.player.next_sample();
+            self.samples.push(sample);
+        }
+    }
+
+    fn set_sample(&mut self, sample: Option<&Sample>) {
+        self.player.set_sample(sample);
+    }
+
+    fn set_tone(&mut self, tone: Tone) {
+        self.generator.set_tone(tone);
+        self.player.set_volume(tone.volume);
+    }
+}
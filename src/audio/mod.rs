@@ -0,0 +1,39 @@
+mod capture;
+mod null;
+mod sample;
+mod speaker;
+mod tone;
+mod wav;
+
+use log::warn;
+
+use crate::interpreter::Sample;
+
+pub use capture::CaptureBackend;
+pub use null::NullBackend;
+pub use sample::SamplePlayer;
+pub use speaker::SpeakerBackend;
+pub use tone::{Tone, ToneGenerator, Waveform};
+pub use wav::write_wav;
+
+pub const SAMPLE_RATE: u32 = 44100;
+pub const FRAME_RATE: u32 = 60;
+
+pub trait AudioBackend {
+    // Called once per 60 Hz frame with the current value of the sound timer.
+    fn update(&mut self, sound_timer: u8);
+    // Called once per frame with the digitised sound the interpreter is playing, if any.
+    fn set_sample(&mut self, sample: Option<&Sample>);
+    fn set_tone(&mut self, tone: Tone);
+}
+
+// Plays through the default output device, falling back to silence when there is none.
+pub fn default_backend(tone: Tone) -> Box<dyn AudioBackend> {
+    match SpeakerBackend::new(tone) {
+        Ok(backend) => Box::new(backend),
+        Err(e) => {
+            warn!("No audio output available ({}). Sound is disabled.", e);
+            Box::new(NullBackend)
+        }
+    }
+}
@@ -0,0 +1,12 @@
+use super::{AudioBackend, Tone};
+use crate::interpreter::Sample;
+
+pub struct NullBackend;
+
+impl AudioBackend for NullBackend {
+    fn update(&mut self, _sound_timer: u8) {}
+
+    fn set_sample(&mut self, _sample: Option<&Sample>) {}
+
+    fn set_tone(&mut self, _tone: Tone) {}
+}
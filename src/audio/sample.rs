@@ -0,0 +1,55 @@
+use crate::interpreter::Sample;
+
+// Plays digitised sounds at the output rate by holding each sample until the next one is due.
+#[derive(Debug, Clone)]
+pub struct SamplePlayer {
+    sample_rate: u32,
+    volume: f32,
+    sample: Option<Sample>,
+    position: f64,
+}
+
+impl SamplePlayer {
+    pub fn new(volume: f32, sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            volume,
+            sample: None,
+            position: 0.0,
+        }
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+    }
+
+    // Starts the sound over whenever the interpreter starts another one, and stops it when the
+    // interpreter has none.
+    pub fn set_sample(&mut self, sample: Option<&Sample>) {
+        match (sample, &self.sample) {
+            (Some(sample), Some(playing)) if sample.id == playing.id => (),
+            (sample, _) => {
+                self.sample = sample.cloned();
+                self.position = 0.0;
+            }
+        }
+    }
+
+    pub fn next_sample(&mut self) -> f32 {
+        let Some(sample) = &self.sample else {
+            return 0.0;
+        };
+
+        let len = sample.data.len();
+        if self.position as usize >= len {
+            match sample.looping && len > 0 {
+                true => self.position %= len as f64,
+                false => return 0.0,
+            }
+        }
+
+        let value = sample.data[self.position as usize];
+        self.position += sample.rate as f64 / self.sample_rate as f64;
+        (value as f32 - 128.0) / 128.0 * self.volume
+    }
+}
@@ -0,0 +1,95 @@
+use anyhow::Result;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use super::{AudioBackend, SamplePlayer, Tone, ToneGenerator, SAMPLE_RATE};
+use crate::interpreter::Sample;
+
+pub struct SpeakerBackend {
+    #[allow(unused)]
+    stream: (rodio::OutputStream, rodio::OutputStreamHandle),
+    #[allow(unused)]
+    sink: rodio::Sink,
+    voices: Arc<Mutex<Voices>>,
+}
+
+// Everything the output stream mixes together.
+struct Voices {
+    generator: ToneGenerator,
+    player: SamplePlayer,
+}
+
+impl SpeakerBackend {
+    pub fn new(tone: Tone) -> Result<Self> {
+        let (stream, handle) = rodio::OutputStream::try_default()?;
+        let sink = rodio::Sink::try_new(&handle)?;
+        let voices = Arc::new(Mutex::new(Voices {
+            generator: ToneGenerator::new(tone, SAMPLE_RATE),
+            player: SamplePlayer::new(tone.volume, SAMPLE_RATE),
+        }));
+        sink.append(ToneSource(Arc::clone(&voices)));
+
+        Ok(Self {
+            stream: (stream, handle),
+            sink,
+            voices,
+        })
+    }
+}
+
+impl AudioBackend for SpeakerBackend {
+    fn update(&mut self, sound_timer: u8) {
+        if let Ok(mut voices) = self.voices.lock() {
+            voices.generator.set_sound_timer(sound_timer);
+        }
+    }
+
+    fn set_sample(&mut self, sample: Option<&Sample>) {
+        if let Ok(mut voices) = self.voices.lock() {
+            voices.player.set_sample(sample);
+        }
+    }
+
+    fn set_tone(&mut self, tone: Tone) {
+        if let Ok(mut voices) = self.voices.lock() {
+            voices.generator.set_tone(tone);
+            voices.player.set_volume(tone.volume);
+        }
+    }
+}
+
+// Never-ending source that pulls samples from the shared voices.
+struct ToneSource(Arc<Mutex<Voices>>);
+
+impl Iterator for ToneSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        Some(
+            self.0
+                .lock()
+                .map(|mut voices| voices.generator.next_sample() + voices.player.next_sample())
+                .unwrap_or(0.0),
+        )
+    }
+}
+
+impl rodio::Source for ToneSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+use super::FRAME_RATE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Waveform {
+    Square,
+    Sine,
+    Triangle,
+    Noise,
+}
+
+impl Waveform {
+    pub const ALL: [Waveform; 4] = [
+        Waveform::Square,
+        Waveform::Sine,
+        Waveform::Triangle,
+        Waveform::Noise,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tone {
+    pub waveform: Waveform,
+    pub frequency: f32,
+    pub volume: f32,
+    pub attack_ms: f32,
+    pub release_ms: f32,
+}
+
+impl Default for Tone {
+    fn default() -> Self {
+        Self {
+            waveform: Waveform::Square,
+            frequency: 329.628,
+            volume: 0.25,
+            attack_ms: 2.0,
+            release_ms: 5.0,
+        }
+    }
+}
+
+// Synthesizes the buzzer one sample at a time. The sound timer is converted into a number of
+// samples, so beeps last exactly as long as the timer says even when they are shorter than a
+// frame, and the envelope ramps the level to avoid clicks when the buzzer starts and stops.
+#[derive(Debug, Clone)]
+pub struct ToneGenerator {
+    tone: Tone,
+    sample_rate: u32,
+    remaining: u32,
+    phase: f32,
+    level: f32,
+    noise: u32,
+    noise_sample: f32,
+}
+
+impl ToneGenerator {
+    pub fn new(tone: Tone, sample_rate: u32) -> Self {
+        Self {
+            tone,
+            sample_rate,
+            remaining: 0,
+            phase: 0.0,
+            level: 0.0,
+            noise: 0x12345678,
+            noise_sample: 0.0,
+        }
+    }
+
+    pub fn set_tone(&mut self, tone: Tone) {
+        self.tone = tone;
+    }
+
+    pub fn set_sound_timer(&mut self, sound_timer: u8) {
+        self.remaining = sound_timer as u32 * self.sample_rate / FRAME_RATE;
+    }
+
+    pub fn next_sample(&mut self) -> f32 {
+        let target = if self.remaining > 0 { 1.0 } else { 0.0 };
+        self.remaining = self.remaining.saturating_sub(1);
+
+        let ramp_ms = match target > self.level {
+            true => self.tone.attack_ms,
+            false => self.tone.release_ms,
+        };
+        let step = match ramp_ms > 0.0 {
+            true => 1000.0 / (ramp_ms * self.sample_rate as f32),
+            false => 1.0,
+        };
+        self.level = match target > self.level {
+            true => (self.level + step).min(target),
+            false => (self.level - step).max(target),
+        };
+
+        if self.level == 0.0 {
+            self.phase = 0.0;
+            return 0.0;
+        }
+
+        let sample = match self.tone.waveform {
+            Waveform::Square => match self.phase < 0.5 {
+                true => 1.0,
+                false => -1.0,
+            },
+            Waveform::Sine => (self.phase * TAU).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * (self.phase - 0.5).abs(),
+            Waveform::Noise => self.noise_sample,
+        };
+
+        let phase = self.phase + self.tone.frequency / self.sample_rate as f32;
+        if phase >= 1.0 {
+            self.noise_sample = self.next_noise();
+        }
+        self.phase = phase.fract();
+
+        sample * self.level * self.tone.volume
+    }
+
+    // xorshift32, mapped to [-1, 1).
+    fn next_noise(&mut self) -> f32 {
+        self.noise ^= self.noise << 13;
+        self.noise ^= self.noise >> 17;
+        self.noise ^= self.noise << 5;
+        (self.noise as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+}
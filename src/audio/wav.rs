@@ -0,0 +1,63 @@
+use std::io::{self, Write};
+
+use super::SAMPLE_RATE;
+
+const CHANNELS: u16 = 1;
+const BITS_PER_SAMPLE: u16 = 16;
+
+// Writes mono samples in [-1, 1] as a 16-bit PCM WAV file.
+pub fn write_wav(mut writer: impl Write, samples: &[f32]) -> io::Result<()> {
+    let block_align = CHANNELS * BITS_PER_SAMPLE / 8;
+    let byte_rate = SAMPLE_RATE * block_align as u32;
+    let data_size = (samples.len() * block_align as usize) as u32;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&CHANNELS.to_le_bytes())?;
+    writer.write_all(&SAMPLE_RATE.to_le_bytes())?;
+    writer.write_all(&byte_rate.to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+    for sample in samples {
+        let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        writer.write_all(&sample.to_le_bytes())?;
+    }
+
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_wav_writes_header_and_samples() {
+        let mut wav = Vec::new();
+        write_wav(&mut wav, &[0.0, 1.0, -1.0, 2.0]).unwrap();
+
+        assert_eq!(wav.len(), 44 + 8);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(wav[4..8], (36u32 + 8).to_le_bytes());
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(wav[22..24], CHANNELS.to_le_bytes());
+        assert_eq!(wav[24..28], SAMPLE_RATE.to_le_bytes());
+        assert_eq!(wav[34..36], BITS_PER_SAMPLE.to_le_bytes());
+        assert_eq!(&wav[36..40], b"data");
+        assert_eq!(wav[40..44], 8u32.to_le_bytes());
+
+        // Samples out of range are clamped.
+        let samples = wav[44..]
+            .chunks(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+            .collect::<Vec<i16>>();
+        assert_eq!(samples, [0, i16::MAX, -i16::MAX, i16::MAX]);
+    }
+}
@@ -0,0 +1,185 @@
+use anyhow::Result;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use crate::interpreter::Interpreter;
+use crate::settings::Palette;
+
+const FRAME_RATE: u64 = 60;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    width: usize,
+    height: usize,
+    // One palette index per pixel, or three RGB bytes per pixel for true colour, row by row.
+    pixels: Vec<u8>,
+    // RGB palette of variants with colour hardware, which replaces the settings' palette.
+    colors: Option<Vec<u8>>,
+    true_color: bool,
+}
+
+impl Frame {
+    pub fn new(interpreter: &dyn Interpreter) -> Self {
+        if let Some(rows) = interpreter.rgb_display() {
+            return Self {
+                width: rows.first().map_or(0, |row| row.len() / 3),
+                height: rows.len(),
+                pixels: rows.concat(),
+                colors: None,
+                true_color: true,
+            };
+        }
+
+        let display = interpreter.display();
+        Self {
+            width: display.first().map_or(0, |row| row.len()),
+            height: display.len(),
+            pixels: display.concat(),
+            colors: interpreter.colors().map(|colors| colors.concat()),
+            true_color: false,
+        }
+    }
+
+    fn bytes_per_pixel(&self) -> usize {
+        match self.true_color {
+            true => 3,
+            false => 1,
+        }
+    }
+
+    fn scaled(&self, scale: usize) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(self.pixels.len() * scale * scale);
+        for row in self.pixels.chunks(self.width * self.bytes_per_pixel()) {
+            let scaled_row = row
+                .chunks(self.bytes_per_pixel())
+                .map(|pixel| match (self.true_color, &self.colors) {
+                    (false, None) => vec![pixel[0].min(1)],
+                    _ => pixel.to_vec(),
+                })
+                .flat_map(|pixel| std::iter::repeat(pixel).take(scale).flatten())
+                .collect::<Vec<u8>>();
+            for _ in 0..scale {
+                pixels.extend_from_slice(&scaled_row);
+            }
+        }
+        pixels
+    }
+}
+
+fn palette_bytes(palette: Palette) -> Vec<u8> {
+    [palette.background, palette.foreground]
+        .iter()
+        .flat_map(|color| [color.r(), color.g(), color.b()])
+        .collect()
+}
+
+pub fn write_png(writer: impl Write, frame: &Frame, scale: usize, palette: Palette) -> Result<()> {
+    let mut encoder = png::Encoder::new(
+        writer,
+        (frame.width * scale) as u32,
+        (frame.height * scale) as u32,
+    );
+    encoder.set_depth(png::BitDepth::Eight);
+    if frame.true_color {
+        encoder.set_color(png::ColorType::Rgb);
+    } else {
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_palette(
+            frame
+                .colors
+                .clone()
+                .unwrap_or_else(|| palette_bytes(palette)),
+        );
+    }
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&frame.scaled(scale))?;
+    writer.finish()?;
+    Ok(())
+}
+
+// Writes successive 60 Hz frames to an animated GIF. Identical consecutive frames are merged
+// into one with a longer delay, and delays are rounded so the animation does not drift.
+pub struct GifRecorder {
+    encoder: gif::Encoder<BufWriter<File>>,
+    width: usize,
+    height: usize,
+    scale: usize,
+    frame_count: u64,
+    pending: Option<(Frame, u64)>,
+}
+
+impl GifRecorder {
+    pub fn new(path: &Path, frame: &Frame, scale: usize, palette: Palette) -> Result<Self> {
+        let mut encoder = gif::Encoder::new(
+            BufWriter::new(File::create(path)?),
+            (frame.width * scale) as u16,
+            (frame.height * scale) as u16,
+            &palette_bytes(palette),
+        )?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+
+        Ok(Self {
+            encoder,
+            width: frame.width,
+            height: frame.height,
+            scale,
+            frame_count: 0,
+            pending: None,
+        })
+    }
+
+    pub fn add_frame(&mut self, frame: Frame) -> Result<()> {
+        if frame.width != self.width || frame.height != self.height {
+            anyhow::bail!("Display size changed during GIF recording");
+        }
+
+        match &self.pending {
+            Some((pending, _)) if *pending == frame => (),
+            _ => {
+                self.flush()?;
+                self.pending = Some((frame, self.frame_count));
+            }
+        }
+
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.flush()?;
+        self.encoder.get_mut().flush()?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        let Some((frame, start)) = self.pending.take() else {
+            return Ok(());
+        };
+
+        // GIF delays are in hundredths of a second.
+        let centiseconds = |frame: u64| frame * 100 / FRAME_RATE;
+        let delay = (centiseconds(self.frame_count) - centiseconds(start)).max(1);
+
+        let (width, height) = (
+            (self.width * self.scale) as u16,
+            (self.height * self.scale) as u16,
+        );
+        // True colour frames are quantized into a palette of their own.
+        let mut gif_frame = match frame.true_color {
+            true => gif::Frame::from_rgb_speed(width, height, &frame.scaled(self.scale), 10),
+            false => {
+                let mut gif_frame =
+                    gif::Frame::from_indexed_pixels(width, height, frame.scaled(self.scale), None);
+                gif_frame.palette = frame.colors;
+                gif_frame
+            }
+        };
+        gif_frame.delay = delay.min(u16::MAX as u64) as u16;
+        self.encoder.write_frame(&gif_frame)?;
+        Ok(())
+    }
+}
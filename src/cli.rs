@@ -0,0 +1,205 @@
+use clap::Parser;
+use std::path::PathBuf;
+
+use crate::app::Mode;
+use crate::interpreter::{BigFont, Font, MachineConfig, SmallFont, MAX_MEMORY_SIZE};
+
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Args {
+    /// ROM to load on startup
+    pub rom: Option<PathBuf>,
+
+    /// Interpreter variant used to run the ROM
+    #[arg(long, value_enum)]
+    pub mode: Option<Mode>,
+
+    /// Number of instructions executed per second
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub speed: Option<u64>,
+
+    /// Size of the emulated memory in bytes
+    #[arg(long, value_name = "BYTES", value_parser = parse_memory_size)]
+    pub memory_size: Option<usize>,
+
+    /// Address the ROM is loaded at and run from, e.g. 0x600 for ETI-660 programs
+    #[arg(long, value_name = "ADDRESS", value_parser = parse_address)]
+    pub program_address: Option<usize>,
+
+    /// Address the font is loaded at
+    #[arg(long, value_name = "ADDRESS", value_parser = parse_address)]
+    pub font_address: Option<usize>,
+
+    /// Font used by FX29: vip, dream6800, eti660, schip, or a file of 80 bytes
+    #[arg(long, value_name = "FONT", value_parser = parse_small_font)]
+    pub font: Option<SmallFont>,
+
+    /// Font used by FX30: schip, octo, fishnchips, or a file of up to 160 bytes
+    #[arg(long, value_name = "FONT", value_parser = parse_big_font)]
+    pub big_font: Option<BigFont>,
+
+    /// Size of the display at the variant's highest resolution, e.g. 64x64
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_display_size)]
+    pub display_size: Option<(usize, usize)>,
+
+    /// Maximum number of nested subroutine calls
+    #[arg(long, value_name = "DEPTH")]
+    pub stack_depth: Option<usize>,
+
+    /// Start with audio muted
+    #[arg(long)]
+    pub mute: bool,
+
+    /// Record the buzzer output to a WAV file
+    #[arg(long, value_name = "FILE")]
+    pub record_audio: Option<PathBuf>,
+
+    /// Replay a recorded movie of the ROM, which records its own mode, speed and machine
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "rom",
+        conflicts_with_all = [
+            "mode",
+            "speed",
+            "memory_size",
+            "program_address",
+            "font_address",
+            "font",
+            "big_font",
+            "display_size",
+            "stack_depth",
+        ]
+    )]
+    pub replay: Option<PathBuf>,
+
+    /// Folder with the CHIP-8 program database's programs.json and sha1-hashes.json, used to
+    /// pick the mode, quirks and speed of known ROMs in headless mode
+    #[arg(long, value_name = "DIRECTORY", requires = "headless")]
+    pub database: Option<PathBuf>,
+
+    /// Run the ROM without opening a window
+    #[arg(long, requires = "rom")]
+    pub headless: bool,
+
+    /// Number of 60 Hz frames to run for in headless mode, or the length of the replayed movie
+    #[arg(long, requires = "headless")]
+    pub frames: Option<u64>,
+}
+
+impl Args {
+    // Applies the machine layout given on the command line on top of `base`.
+    pub fn machine(&self, base: MachineConfig) -> MachineConfig {
+        let mut machine = base;
+        if let Some(size) = self.memory_size {
+            machine = machine.memory_size(size);
+        }
+        if let Some(address) = self.program_address {
+            machine = machine.program_address(address);
+        }
+        if let Some(address) = self.font_address {
+            machine = machine.font_address(address);
+        }
+        if self.font.is_some() || self.big_font.is_some() {
+            let font = Font {
+                small: self.font.clone().unwrap_or(machine.font.small.clone()),
+                big: self.big_font.clone().unwrap_or(machine.font.big.clone()),
+            };
+            machine = machine.font(font);
+        }
+        if let Some((width, height)) = self.display_size {
+            machine = machine.display_size(width, height);
+        }
+        if let Some(depth) = self.stack_depth {
+            machine = machine.stack_depth(depth);
+        }
+        machine
+    }
+}
+
+// Accepts numbers in hexadecimal with a 0x prefix, or in decimal.
+fn parse_number(s: &str) -> Result<usize, String> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|e| e.to_string())
+}
+
+fn parse_address(s: &str) -> Result<usize, String> {
+    let address = parse_number(s)?;
+    match address < MAX_MEMORY_SIZE {
+        true => Ok(address),
+        false => Err(format!("{:#x} is past the end of memory", address)),
+    }
+}
+
+fn parse_memory_size(s: &str) -> Result<usize, String> {
+    let size = parse_number(s)?;
+    match size <= MAX_MEMORY_SIZE {
+        true => Ok(size),
+        false => Err(format!("memory can be at most {} bytes", MAX_MEMORY_SIZE)),
+    }
+}
+
+// Accepts the name of a built-in font, or else the path to a font file.
+fn parse_small_font(s: &str) -> Result<SmallFont, String> {
+    match s {
+        "vip" => Ok(SmallFont::Vip),
+        "dream6800" => Ok(SmallFont::Dream6800),
+        "eti660" => Ok(SmallFont::Eti660),
+        "schip" | "octo" => Ok(SmallFont::Schip),
+        path => SmallFont::custom(read_font(path)?).map_err(|e| e.to_string()),
+    }
+}
+
+fn parse_big_font(s: &str) -> Result<BigFont, String> {
+    match s {
+        "schip" => Ok(BigFont::Schip),
+        "octo" => Ok(BigFont::Octo),
+        "fishnchips" => Ok(BigFont::FishNChips),
+        path => BigFont::custom(read_font(path)?).map_err(|e| e.to_string()),
+    }
+}
+
+fn read_font(path: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("not a built-in font, and failed to read it: {}", e))
+}
+
+fn parse_display_size(s: &str) -> Result<(usize, usize), String> {
+    let (width, height) = s
+        .split_once('x')
+        .ok_or_else(|| "expected a size such as 64x32".to_string())?;
+    let size = (
+        width
+            .parse()
+            .map_err(|e: std::num::ParseIntError| e.to_string())?,
+        height
+            .parse()
+            .map_err(|e: std::num::ParseIntError| e.to_string())?,
+    );
+
+    match size.0 > 0 && size.1 > 0 {
+        true => Ok(size),
+        false => Err("the display needs at least one pixel".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_rejects_overridden_settings() {
+        let replay = ["octarou", "rom.ch8", "--replay", "rom.movie"];
+        assert!(Args::try_parse_from(replay).is_ok());
+        for option in [
+            ["--mode", "chip8"],
+            ["--speed", "1000"],
+            ["--memory-size", "4096"],
+        ] {
+            let args = replay.iter().chain(&option);
+            assert!(Args::try_parse_from(args).is_err());
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use anyhow::Result;
+use serde::Deserialize;
+use std::{collections::HashMap, path::Path};
+
+use crate::app::Mode;
+use crate::interpreter::Quirks;
+use crate::settings::Palette;
+
+// The database is read from a copy of the `database` folder of the CHIP-8 program database
+// (https://github.com/chip-8/chip-8-database), which is too large to embed and changes often.
+const PROGRAMS_FILE: &str = "programs.json";
+const HASHES_FILE: &str = "sha1-hashes.json";
+
+#[derive(Debug, Clone, Deserialize)]
+struct ProgramEntry {
+    title: String,
+    #[serde(default)]
+    authors: Vec<String>,
+    #[serde(default)]
+    roms: HashMap<String, RomEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RomEntry {
+    #[serde(default)]
+    platforms: Vec<String>,
+    #[serde(default)]
+    quirky_platforms: HashMap<String, Quirks>,
+    tickrate: Option<u64>,
+    colors: Option<Colors>,
+    #[serde(default)]
+    keys: HashMap<String, u8>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Colors {
+    #[serde(default)]
+    pixels: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Database {
+    programs: Vec<ProgramEntry>,
+    // Indices into `programs` by the SHA-1 hash of each ROM.
+    hashes: HashMap<String, usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metadata {
+    pub title: String,
+    pub authors: Vec<String>,
+    pub platform: String,
+    pub mode: Option<Mode>,
+    pub quirks: Quirks,
+    pub speed: Option<u64>,
+    pub palette: Option<Palette>,
+    pub keys: Vec<(egui::Key, u8)>,
+}
+
+impl Database {
+    pub fn load(directory: &Path) -> Result<Self> {
+        let read = |file: &str| {
+            let path = directory.join(file);
+            std::fs::read_to_string(&path)
+                .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))
+        };
+
+        Self::from_json(&read(PROGRAMS_FILE)?, &read(HASHES_FILE)?).map_err(|e| {
+            anyhow::anyhow!(
+                "{} is not a valid program database: {}",
+                directory.display(),
+                e
+            )
+        })
+    }
+
+    fn from_json(programs: &str, hashes: &str) -> serde_json::Result<Self> {
+        Ok(Self {
+            programs: serde_json::from_str(programs)?,
+            hashes: serde_json::from_str(hashes)?,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.programs.len()
+    }
+
+    pub fn lookup(&self, hash: &str) -> Option<Metadata> {
+        let program = self.programs.get(*self.hashes.get(hash)?)?;
+        let rom = program.roms.get(hash)?;
+
+        // Prefer the first platform we can actually run, but still report the preferred one.
+        let platform = rom
+            .platforms
+            .iter()
+            .find(|platform| platform_mode(platform).is_some())
+            .or(rom.platforms.first())?
+            .clone();
+
+        Some(Metadata {
+            title: program.title.clone(),
+            authors: program.authors.clone(),
+            mode: platform_mode(&platform),
+            quirks: rom
+                .quirky_platforms
+                .get(&platform)
+                .copied()
+                .unwrap_or_else(|| platform_quirks(&platform)),
+            platform,
+            speed: rom.tickrate.map(|tickrate| tickrate * 60),
+            palette: rom.colors.as_ref().and_then(|colors| {
+                Some(Palette {
+                    background: parse_color(colors.pixels.first()?)?,
+                    foreground: parse_color(colors.pixels.get(1)?)?,
+                })
+            }),
+            keys: rom
+                .keys
+                .iter()
+                .filter_map(|(name, &key)| Some((action_key(name)?, key)))
+                .collect(),
+        })
+    }
+}
+
+fn platform_mode(platform: &str) -> Option<Mode> {
+    match platform {
+        "originalChip8" | "hybridVIP" | "modernChip8" => Some(Mode::Chip8),
+        "chip8x" => Some(Mode::Chip8X),
+        "chip48" | "superchip1" => Some(Mode::SuperChip10),
+        "superchip" => Some(Mode::SuperChip11),
+        "megachip8" => Some(Mode::MegaChip),
+        _ => None,
+    }
+}
+
+// Default quirks of each platform, as listed in the database's `platforms.json`.
+fn platform_quirks(platform: &str) -> Quirks {
+    match platform {
+        "originalChip8" | "hybridVIP" | "chip8x" => Quirks {
+            memory_leave_i_unchanged: false,
+            logic: true,
+            ..Quirks::CHIP8
+        },
+        "modernChip8" => Quirks {
+            memory_leave_i_unchanged: false,
+            ..Quirks::CHIP8
+        },
+        "chip48" | "superchip1" => Quirks::SUPERCHIP_1_0,
+        "superchip" | "megachip8" => Quirks::SUPERCHIP,
+        _ => Quirks::default(),
+    }
+}
+
+fn action_key(name: &str) -> Option<egui::Key> {
+    match name {
+        "up" => Some(egui::Key::ArrowUp),
+        "down" => Some(egui::Key::ArrowDown),
+        "left" => Some(egui::Key::ArrowLeft),
+        "right" => Some(egui::Key::ArrowRight),
+        "a" => Some(egui::Key::Space),
+        "b" => Some(egui::Key::Enter),
+        _ => None,
+    }
+}
+
+fn parse_color(color: &str) -> Option<egui::Color32> {
+    let hex = color.strip_prefix('#')?;
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    match hex.len() {
+        6 => Some(egui::Color32::from_rgb(
+            (value >> 16) as u8,
+            (value >> 8) as u8,
+            value as u8,
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Entries in the upstream format, trimmed to the fields that are read.
+    const PROGRAMS: &str = r##"[
+        {
+            "title": "Pong",
+            "authors": ["Paul Vervalin"],
+            "roms": {
+                "HASH": {
+                    "platforms": ["xochip", "superchip"],
+                    "quirkyPlatforms": {
+                        "superchip": {
+                            "shift": true,
+                            "memoryIncrementByX": false,
+                            "memoryLeaveIUnchanged": true,
+                            "wrap": true,
+                            "jump": true,
+                            "vblank": false,
+                            "logic": false
+                        }
+                    },
+                    "tickrate": 30,
+                    "colors": { "pixels": ["#000000", "#ff8000"] },
+                    "keys": { "up": 1, "down": 4 }
+                }
+            }
+        },
+        {
+            "title": "Octopeg",
+            "roms": { "OTHER": { "platforms": ["xochip"] } }
+        }
+    ]"##;
+
+    fn database(rom: &[u8]) -> (Database, String) {
+        let hash = sha1_smol::Sha1::from(rom).digest().to_string();
+        let programs = PROGRAMS.replace("\"HASH\"", &format!("\"{}\"", hash));
+        let hashes = format!(r#"{{ "{}": 0, "OTHER": 1 }}"#, hash);
+        (Database::from_json(&programs, &hashes).unwrap(), hash)
+    }
+
+    #[test]
+    fn lookup_resolves_rom_hash() {
+        let (database, hash) = database(&[0x00, 0xE0, 0x12, 0x02]);
+        let metadata = database.lookup(&hash).unwrap();
+
+        assert_eq!(metadata.title, "Pong");
+        assert_eq!(metadata.authors, ["Paul Vervalin"]);
+        assert_eq!(metadata.platform, "superchip");
+        assert_eq!(metadata.mode, Some(Mode::SuperChip11));
+        assert_eq!(
+            metadata.quirks,
+            Quirks {
+                wrap: true,
+                ..Quirks::SUPERCHIP
+            }
+        );
+        assert_eq!(metadata.speed, Some(1800));
+        assert_eq!(
+            metadata.palette,
+            Some(Palette {
+                background: egui::Color32::BLACK,
+                foreground: egui::Color32::from_rgb(0xFF, 0x80, 0x00),
+            })
+        );
+        let mut keys = metadata.keys;
+        keys.sort_by_key(|&(_, key)| key);
+        assert_eq!(keys, [(egui::Key::ArrowUp, 1), (egui::Key::ArrowDown, 4)]);
+
+        assert_eq!(database.lookup("unknown"), None);
+    }
+
+    #[test]
+    fn lookup_reports_unsupported_platforms() {
+        let (database, _) = database(&[]);
+        let metadata = database.lookup("OTHER").unwrap();
+
+        assert_eq!(metadata.platform, "xochip");
+        assert_eq!(metadata.mode, None);
+        assert_eq!(metadata.quirks, Quirks::default());
+    }
+}
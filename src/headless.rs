@@ -0,0 +1,93 @@
+use anyhow::Result;
+use std::{fs::File, io::BufWriter};
+
+use crate::app::{Mode, Program};
+use crate::audio::{self, AudioBackend, CaptureBackend, Tone};
+use crate::cli::Args;
+use crate::database::Database;
+use crate::interpreter::{self, Keypad, MachineConfig};
+use crate::movie::Movie;
+use crate::settings::Settings;
+
+const DEFAULT_FRAMES: u64 = 600;
+
+pub fn run(args: &Args, program: Program) -> Result<()> {
+    // Without a movie to replay, an empty one describes how to run the ROM.
+    let movie = match &args.replay {
+        Some(path) => Movie::load(path)?,
+        None => configure(args, &program)?,
+    };
+    let mut interpreter = movie.interpreter(&program)?;
+    let frames = args
+        .frames
+        .or(args.replay.is_some().then_some(movie.frames.len() as u64))
+        .unwrap_or(DEFAULT_FRAMES);
+
+    eprintln!(
+        "Running {} in {:?} mode for {} frames.",
+        program.filename(),
+        movie.mode,
+        frames
+    );
+
+    // Frames are run back to back rather than in real time, so runs are reproducible.
+    let mut capture = CaptureBackend::new(Tone::default());
+    let mut keypad = Keypad::default();
+    let result = (0..frames as usize).try_for_each(|frame| {
+        let (keys_down, keys_released) = movie.keys(frame).unwrap_or_default();
+        let time = frame as f64 / 60.0;
+        keypad.set_state(&keys_down, &keys_released, time);
+        keypad.advance(time);
+        let result = interpreter.run_frame(&mut keypad, movie.instructions_per_frame());
+        capture.update(interpreter.sound_timer());
+        capture.set_sample(interpreter.sample());
+        result
+    });
+
+    // The audio leading up to a fault is still saved.
+    if let Some(path) = &args.record_audio {
+        audio::write_wav(BufWriter::new(File::create(path)?), capture.samples())?;
+        eprintln!("Saved audio recording to {}.", path.display());
+    }
+
+    Ok(result?)
+}
+
+fn configure(args: &Args, program: &Program) -> Result<Movie> {
+    let metadata = match &args.database {
+        Some(directory) => Database::load(directory)?.lookup(program.hash()),
+        None => None,
+    };
+    if let Some(metadata) = &metadata {
+        eprintln!(
+            "Recognised {} by {} ({}).",
+            metadata.title,
+            metadata.authors.join(", "),
+            metadata.platform
+        );
+    }
+
+    let machine = args.machine(MachineConfig::new());
+    let mode = args
+        .mode
+        .or(metadata.as_ref().and_then(|metadata| metadata.mode))
+        .unwrap_or_else(|| Mode::from(interpreter::detect(program.data(), &machine).variant));
+    // Quirks from the database only apply to the platform they were listed for.
+    let quirks = metadata
+        .as_ref()
+        .filter(|metadata| metadata.mode == Some(mode))
+        .map_or_else(|| mode.default_quirks(), |metadata| metadata.quirks);
+    let speed = args
+        .speed
+        .or(metadata.as_ref().and_then(|metadata| metadata.speed))
+        .unwrap_or(Settings::default().speed);
+
+    Ok(Movie::new(
+        program,
+        mode,
+        quirks,
+        machine,
+        speed,
+        rand::random(),
+    ))
+}
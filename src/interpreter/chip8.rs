@@ -1,58 +1,81 @@
-use super::{instruction::Instruction, Interpreter, InterpreterError};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
-const MEMORY_SIZE: usize = 4096;
-const PROGRAM_ADDRESS: usize = 0x200;
+use super::{
+    check_bounds,
+    font::SMALL_GLYPH_SIZE,
+    instruction::Instruction,
+    machine::{Layout, MachineConfig},
+    snapshot_memory,
+    stack::Stack,
+    ErrorKind, Interpreter, Keypad, Quirks, Snapshot, Variant,
+};
 
-const FONT_ADDRESS: usize = 0x50;
-const FONT: [u8; 80] = [
-    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-    0x20, 0x60, 0x20, 0x20, 0x70, // 1
-    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
-];
-
-const DISPLAY_WIDTH: usize = 64;
-const DISPLAY_HEIGHT: usize = 32;
+pub(super) const LAYOUT: Layout = Layout {
+    memory_size: 4096,
+    program_address: 0x200,
+    font_address: 0x50,
+    display_size: (64, 32),
+};
+// The COSMAC VIP has room for 12 nested calls.
+const STACK_DEPTH: usize = 12;
 
 pub struct Chip8 {
-    memory: [u8; MEMORY_SIZE],
+    memory: Vec<u8>,
     pc: usize,
     index: usize,
-    stack: Vec<usize>,
+    stack: Stack,
     delay_timer: u8,
     sound_timer: u8,
     variables: [u8; 16],
-    display: [[u8; DISPLAY_WIDTH]; DISPLAY_HEIGHT],
+    font_address: usize,
+    display: Vec<Vec<u8>>,
+    quirks: Quirks,
+    rng: StdRng,
 }
 
 impl Chip8 {
-    pub fn new(program: &[u8]) -> Self {
-        let mut memory = [0u8; MEMORY_SIZE];
-        memory[FONT_ADDRESS..FONT_ADDRESS + FONT.len()].copy_from_slice(&FONT);
-        memory[PROGRAM_ADDRESS..PROGRAM_ADDRESS + program.len()].copy_from_slice(program);
+    pub fn new(program: &[u8], config: &MachineConfig) -> Result<Self, ErrorKind> {
+        Self::with_layout(program, config, LAYOUT, 0)
+    }
+
+    // Builds the interpreter with another default layout, for variants that only differ from
+    // CHIP-8 in their display, and starts running `entry_offset` bytes into the program.
+    pub(super) fn with_layout(
+        program: &[u8],
+        config: &MachineConfig,
+        defaults: Layout,
+        entry_offset: usize,
+    ) -> Result<Self, ErrorKind> {
+        let layout = config.layout(defaults)?;
 
-        Self {
-            memory,
-            pc: PROGRAM_ADDRESS,
+        Ok(Self {
+            memory: layout.memory(&config.font.data(false)?, program)?,
+            pc: layout.program_address + entry_offset,
             index: 0,
-            stack: vec![],
+            stack: Stack::new(config.stack, STACK_DEPTH),
             delay_timer: 0,
             sound_timer: 0,
             variables: [0; 16],
-            display: [[0; DISPLAY_WIDTH]; DISPLAY_HEIGHT],
-        }
+            font_address: layout.font_address,
+            display: layout.display(),
+            quirks: Quirks::CHIP8,
+            rng: StdRng::from_entropy(),
+        })
+    }
+
+    pub fn with_quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    // For variants built on top of CHIP-8 with instructions of their own.
+    pub(super) fn registers_mut(&mut self) -> &mut [u8; 16] {
+        &mut self.variables
     }
 }
 
@@ -61,8 +84,28 @@ impl Interpreter for Chip8 {
         self.display.iter().map(|row| row.as_slice()).collect()
     }
 
-    fn is_beeping(&self) -> bool {
-        self.sound_timer > 0
+    fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            pc: self.pc,
+            index: self.index,
+            registers: self.variables,
+            stack: self.stack.entries(&self.memory),
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            memory: snapshot_memory(&self.memory, self.pc, self.index),
+        }
+    }
+
+    fn variant(&self) -> Variant {
+        Variant::Chip8
+    }
+
+    fn pc(&self) -> usize {
+        self.pc
     }
 
     fn update_timers(&mut self) {
@@ -70,48 +113,59 @@ impl Interpreter for Chip8 {
         self.sound_timer = self.sound_timer.saturating_sub(1);
     }
 
-    fn next_instruction(&mut self) -> Result<Instruction, InterpreterError> {
+    fn fetch(&mut self) -> Result<u16, ErrorKind> {
         let opcode = u16::from_be_bytes(
             self.memory
                 .get(self.pc..self.pc + 2)
-                .ok_or(InterpreterError::OutOfMemory)?
+                .ok_or(ErrorKind::OutOfMemory)?
                 .try_into()
                 .expect("Slice should always have length 2"),
         );
 
         self.pc += 2;
-        Instruction::new(opcode).ok_or(InterpreterError::UnknownOpcode {
-            opcode,
-            address: self.pc,
-        })
+        Ok(opcode)
     }
 
     fn execute_instruction(
         &mut self,
         instruction: Instruction,
-        keys_down: &[bool; 16],
-        keys_released: &[bool; 16],
-    ) -> Result<(), InterpreterError> {
+        keypad: &mut Keypad,
+    ) -> Result<(), ErrorKind> {
         use Instruction::*;
         match instruction {
-            ClearScreen => Ok(self.display.iter_mut().for_each(|e| e.fill(0))),
-            Jump { address } => Ok(self.pc = address),
+            ClearScreen => {
+                self.display.iter_mut().for_each(|e| e.fill(0));
+                Ok(())
+            }
+            Jump { address } => {
+                self.pc = address;
+                Ok(())
+            }
             JumpOffset {
                 address,
-                offset_register: _,
+                offset_register,
             } => {
-                self.pc = address + self.variables[0] as usize;
+                let offset_register = if self.quirks.jump { offset_register } else { 0 };
+                let target = address + self.variables[offset_register] as usize;
+                check_bounds(&self.memory, target, 2, instruction)?;
+                self.pc = target;
+                Ok(())
+            }
+            SetLiteral { dest, value } => {
+                self.variables[dest] = value;
                 Ok(())
             }
-            SetLiteral { dest, value } => Ok(self.variables[dest] = value),
             AddLiteral { dest, value } => {
                 self.variables[dest] = self.variables[dest].wrapping_add(value);
                 Ok(())
             }
-            SetIndex { src } => Ok(self.index = src),
+            SetIndex { src } => {
+                self.index = src;
+                Ok(())
+            }
             SetIndexFont { src, big: _ } => {
                 let character = (self.variables[src] & 0x0F) as usize;
-                self.index = FONT_ADDRESS + 5 * character;
+                self.index = self.font_address + SMALL_GLYPH_SIZE * character;
                 Ok(())
             }
             AddIndex { src } => {
@@ -121,12 +175,12 @@ impl Interpreter for Chip8 {
                 Ok(())
             }
             Call { address } => {
-                self.stack.push(self.pc);
+                self.stack.push(&mut self.memory, self.pc)?;
                 self.pc = address;
                 Ok(())
             }
             Return => {
-                self.pc = self.stack.pop().ok_or(InterpreterError::PopOutOfMain)?;
+                self.pc = self.stack.pop(&self.memory)?;
                 Ok(())
             }
             SkipEq { x, y } => {
@@ -154,20 +208,28 @@ impl Interpreter for Chip8 {
                 Ok(())
             }
             SkipIfKey { key_register } => {
-                if keys_down[self.variables[key_register] as usize] {
+                let key = self.variables[key_register];
+                if keypad
+                    .is_down(key)
+                    .ok_or(ErrorKind::InvalidKey { key, instruction })?
+                {
                     self.pc += 2;
                 }
                 Ok(())
             }
             SkipIfNotKey { key_register } => {
-                if !keys_down[self.variables[key_register] as usize] {
+                let key = self.variables[key_register];
+                if !keypad
+                    .is_down(key)
+                    .ok_or(ErrorKind::InvalidKey { key, instruction })?
+                {
                     self.pc += 2;
                 }
                 Ok(())
             }
             GetKey { dest } => {
-                if let Some(key) = keys_released.iter().position(|&e| e) {
-                    self.variables[dest] = key as u8;
+                if let Some(key) = keypad.get_key() {
+                    self.variables[dest] = key;
                 } else {
                     self.pc -= 2;
                 }
@@ -179,14 +241,23 @@ impl Interpreter for Chip8 {
             }
             Or { lhs, rhs } => {
                 self.variables[lhs] |= self.variables[rhs];
+                if self.quirks.logic {
+                    self.variables[0xF] = 0;
+                }
                 Ok(())
             }
             And { lhs, rhs } => {
                 self.variables[lhs] &= self.variables[rhs];
+                if self.quirks.logic {
+                    self.variables[0xF] = 0;
+                }
                 Ok(())
             }
             Xor { lhs, rhs } => {
                 self.variables[lhs] ^= self.variables[rhs];
+                if self.quirks.logic {
+                    self.variables[0xF] = 0;
+                }
                 Ok(())
             }
             Add { lhs, rhs } => {
@@ -202,14 +273,18 @@ impl Interpreter for Chip8 {
                 Ok(())
             }
             LeftShift { lhs, rhs } => {
-                self.variables[lhs] = self.variables[rhs];
+                if !self.quirks.shift {
+                    self.variables[lhs] = self.variables[rhs];
+                }
                 let flag = self.variables[lhs] >> 7;
                 self.variables[lhs] <<= 1;
                 self.variables[0xF] = flag;
                 Ok(())
             }
             RightShift { lhs, rhs } => {
-                self.variables[lhs] = self.variables[rhs];
+                if !self.quirks.shift {
+                    self.variables[lhs] = self.variables[rhs];
+                }
                 let flag = self.variables[lhs] & 1;
                 self.variables[lhs] >>= 1;
                 self.variables[0xF] = flag;
@@ -232,31 +307,36 @@ impl Interpreter for Chip8 {
                 y,
                 sprite_height,
             } => {
-                let x = self.variables[x] as usize % DISPLAY_WIDTH;
-                let y = self.variables[y] as usize % DISPLAY_HEIGHT;
+                check_bounds(&self.memory, self.index, sprite_height, instruction)?;
+                let (width, height) = (self.display[0].len(), self.display.len());
+                let x = self.variables[x] as usize % width;
+                let y = self.variables[y] as usize % height;
                 self.variables[0xF] = 0;
 
                 for y_offset in 0..sprite_height {
-                    if y + y_offset >= DISPLAY_HEIGHT {
+                    if y + y_offset >= height && !self.quirks.wrap {
                         break;
                     }
+                    let row = (y + y_offset) % height;
                     let sprite_row = self.memory[self.index + y_offset];
                     for x_offset in 0..8 {
-                        if x + x_offset >= DISPLAY_WIDTH {
+                        if x + x_offset >= width && !self.quirks.wrap {
                             break;
                         }
+                        let column = (x + x_offset) % width;
                         let pixel = (sprite_row >> (7 - x_offset)) & 1;
-                        self.variables[0xF] |= self.display[y + y_offset][x + x_offset] & pixel;
-                        self.display[y + y_offset][x + x_offset] ^= pixel;
+                        self.variables[0xF] |= self.display[row][column] & pixel;
+                        self.display[row][column] ^= pixel;
                     }
                 }
                 Ok(())
             }
             Random { x, mask } => {
-                self.variables[x] = rand::random::<u8>() & mask;
+                self.variables[x] = self.rng.gen::<u8>() & mask;
                 Ok(())
             }
             DecimalConversion { src } => {
+                check_bounds(&self.memory, self.index, 3, instruction)?;
                 let mut n = self.variables[src];
 
                 for i in (0..3).rev() {
@@ -266,20 +346,22 @@ impl Interpreter for Chip8 {
                 Ok(())
             }
             StoreMemory { registers } => {
+                check_bounds(&self.memory, self.index, registers + 1, instruction)?;
                 for i in 0..=registers {
                     self.memory[self.index + i] = self.variables[i];
-                    // self.index = i;
                 }
+                self.index += self.quirks.index_increment(registers);
                 Ok(())
             }
             LoadMemory { registers } => {
+                check_bounds(&self.memory, self.index, registers + 1, instruction)?;
                 for i in 0..=registers {
                     self.variables[i] = self.memory[self.index + i];
-                    // self.index = i;
                 }
+                self.index += self.quirks.index_increment(registers);
                 Ok(())
             }
-            _ => Err(InterpreterError::Chip8InvalidInstruction { instruction }.into()),
+            _ => Err(ErrorKind::Chip8InvalidInstruction { instruction }),
         }
     }
 }
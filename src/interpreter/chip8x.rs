@@ -0,0 +1,207 @@
+use super::{
+    chip8::Chip8,
+    instruction::Instruction,
+    machine::{Layout, MachineConfig},
+    ErrorKind, Interpreter, Keypad, Quirks, Snapshot, Variant,
+};
+
+// The CHIP-8X interpreter takes up the first 0x300 bytes of memory.
+pub(super) const LAYOUT: Layout = Layout {
+    memory_size: 4096,
+    program_address: 0x300,
+    font_address: 0x50,
+    display_size: (64, 32),
+};
+
+// Colours of the VP-590 colour board: 8 foreground colours followed by 4 background colours. The
+// display holds the index of each pixel's colour in this table.
+const COLORS: [[u8; 3]; 12] = [
+    [0x18, 0x18, 0x18], // Black
+    [0xFF, 0x00, 0x00], // Red
+    [0x00, 0x00, 0xFF], // Blue
+    [0xFF, 0x00, 0xFF], // Violet
+    [0x00, 0xFF, 0x00], // Green
+    [0xFF, 0xFF, 0x00], // Yellow
+    [0x00, 0xFF, 0xFF], // Aqua
+    [0xFF, 0xFF, 0xFF], // White
+    [0x00, 0x00, 0x80], // Dark blue
+    [0x00, 0x00, 0x00], // Black
+    [0x00, 0x80, 0x00], // Dark green
+    [0x80, 0x00, 0x00], // Dark red
+];
+const FOREGROUND_COLORS: u8 = 8;
+const BACKGROUND_COLORS: u8 = 4;
+const RED: u8 = 1;
+
+// Colour is set for zones 8 pixels wide. BXY0 colours blocks of 4 rows at a time, BXYN single rows.
+const ZONE_WIDTH: usize = 8;
+const ZONE_HEIGHT: usize = 4;
+
+// CHIP-8X, for the COSMAC VIP with the VP-590 colour board. It is CHIP-8 with a foreground colour
+// for every zone of the display, a background colour for the whole display, and a second keypad.
+pub struct Chip8X {
+    chip8: Chip8,
+    // Foreground colour of each row of each zone, indexed by pixel row and zone column.
+    zones: Vec<Vec<u8>>,
+    background: u8,
+    // Colour index of every pixel, kept up to date with the monochrome display underneath.
+    display: Vec<Vec<u8>>,
+}
+
+impl Chip8X {
+    pub fn new(program: &[u8], config: &MachineConfig) -> Result<Self, ErrorKind> {
+        let chip8 = Chip8::with_layout(program, config, LAYOUT, 0)?;
+        let display = chip8
+            .display()
+            .iter()
+            .map(|row| row.to_vec())
+            .collect::<Vec<Vec<u8>>>();
+        let columns = display[0].len().div_ceil(ZONE_WIDTH);
+
+        let mut chip8x = Self {
+            chip8,
+            zones: vec![vec![RED; columns]; display.len()],
+            background: 0,
+            display,
+        };
+        chip8x.compose();
+        Ok(chip8x)
+    }
+
+    pub fn with_quirks(mut self, quirks: Quirks) -> Self {
+        self.chip8 = self.chip8.with_quirks(quirks);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.chip8 = self.chip8.with_seed(seed);
+        self
+    }
+
+    // Colours every pixel with its zone's colour if it is lit, or the background colour if not.
+    fn compose(&mut self) {
+        let background = FOREGROUND_COLORS + self.background;
+        for (y, row) in self.chip8.display().iter().enumerate() {
+            for (x, &pixel) in row.iter().enumerate() {
+                self.display[y][x] = match pixel {
+                    0 => background,
+                    _ => self.zones[y][x / ZONE_WIDTH],
+                };
+            }
+        }
+    }
+
+    fn color_zones(&mut self, columns: u8, rows: u8, color: u8) {
+        let (first_column, column_count) = ((columns & 0xF) as usize, (columns >> 4) as usize);
+        let (first_row, row_count) = ((rows & 0xF) as usize, (rows >> 4) as usize);
+
+        for zone_row in first_row..=first_row + row_count {
+            for y in zone_row * ZONE_HEIGHT..(zone_row + 1) * ZONE_HEIGHT {
+                self.color_row(y, first_column..=first_column + column_count, color);
+            }
+        }
+    }
+
+    fn color_row(&mut self, y: usize, columns: impl Iterator<Item = usize>, color: u8) {
+        let Some(row) = self.zones.get_mut(y) else {
+            return;
+        };
+        for column in columns {
+            if let Some(zone) = row.get_mut(column) {
+                *zone = color;
+            }
+        }
+    }
+}
+
+impl Interpreter for Chip8X {
+    fn display(&self) -> Vec<&[u8]> {
+        self.display.iter().map(|row| row.as_slice()).collect()
+    }
+
+    fn sound_timer(&self) -> u8 {
+        self.chip8.sound_timer()
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        self.chip8.snapshot()
+    }
+
+    fn variant(&self) -> Variant {
+        Variant::Chip8X
+    }
+
+    fn pc(&self) -> usize {
+        self.chip8.pc()
+    }
+
+    fn colors(&self) -> Option<&[[u8; 3]]> {
+        Some(&COLORS)
+    }
+
+    fn decode(&self, opcode: u16) -> Option<Instruction> {
+        Instruction::new_chip8x(opcode)
+    }
+
+    fn update_timers(&mut self) {
+        self.chip8.update_timers()
+    }
+
+    fn fetch(&mut self) -> Result<u16, ErrorKind> {
+        self.chip8.fetch()
+    }
+
+    fn execute_instruction(
+        &mut self,
+        instruction: Instruction,
+        keypad: &mut Keypad,
+    ) -> Result<(), ErrorKind> {
+        use Instruction::*;
+        let recolor = matches!(
+            instruction,
+            ClearScreen | Draw { .. } | StepBackground | ColorZones { .. } | ColorRows { .. }
+        );
+
+        match instruction {
+            StepBackground => {
+                self.background = (self.background + 1) % BACKGROUND_COLORS;
+            }
+            AddNibbles { x, y } => {
+                let registers = self.chip8.registers_mut();
+                registers[x] = ((registers[x] & 0x77) + (registers[y] & 0x77)) & 0x77;
+            }
+            ColorZones { x, y } => {
+                let registers = self.chip8.registers_mut();
+                let (columns, rows) = (registers[x], registers[(x + 1) % 16]);
+                let color = registers[y] % FOREGROUND_COLORS;
+                self.color_zones(columns, rows, color);
+            }
+            ColorRows { x, y, rows } => {
+                let registers = self.chip8.registers_mut();
+                let column = registers[x] as usize / ZONE_WIDTH;
+                let first_row = registers[(x + 1) % 16] as usize;
+                let color = registers[y] % FOREGROUND_COLORS;
+                for y in first_row..first_row + rows {
+                    self.color_row(y, std::iter::once(column), color);
+                }
+            }
+            // The second keypad shares its keys with the first.
+            SkipIfKeypad2 { key_register } => {
+                return self
+                    .chip8
+                    .execute_instruction(SkipIfKey { key_register }, keypad);
+            }
+            SkipIfNotKeypad2 { key_register } => {
+                return self
+                    .chip8
+                    .execute_instruction(SkipIfNotKey { key_register }, keypad);
+            }
+            _ => self.chip8.execute_instruction(instruction, keypad)?,
+        }
+
+        if recolor {
+            self.compose();
+        }
+        Ok(())
+    }
+}
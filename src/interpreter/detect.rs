@@ -0,0 +1,300 @@
+use std::collections::BTreeSet;
+
+use super::{chip8, chip8x, instruction::Instruction, machine::MachineConfig};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Variant {
+    Chip8,
+    HiresChip8,
+    Chip8X,
+    SuperChip,
+    MegaChip,
+    XoChip,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Detection {
+    pub variant: Variant,
+    // Extended opcodes reached from the entry point, as (address, opcode, variant) triples.
+    pub evidence: Vec<(usize, u16, Variant)>,
+}
+
+// Hi-res CHIP-8 programs start by jumping into a patch of the interpreter at 0x260.
+const HIRES_ENTRY: u16 = 0x1260;
+// Turns MEGA-CHIP mode on.
+const MEGA_ON: u16 = 0x0011;
+// CHIP-8X's 02A0 and the hi-res CHIP-8 0230 both look like MEGA-CHIP's 02NN. 02A0 is the only
+// opcode counted as CHIP-8X: its BXYN and 5XY1 are valid CHIP-8 as well, and its EXF2 and EXF5 are
+// too rare to be worth telling apart from data.
+const CHIP8X_STEP_BACKGROUND: u16 = 0x02A0;
+const HIRES_CLEAR_SCREEN: u16 = 0x0230;
+
+// Statically walks the program from its entry point, following jumps, calls and skips, and
+// reports which extended opcodes are reachable. Code reached only through BNNN or self-modifying
+// code is not seen, so this is a heuristic rather than a proof.
+pub fn detect(program: &[u8], config: &MachineConfig) -> Detection {
+    let program_address = config
+        .program_address
+        .unwrap_or(chip8::LAYOUT.program_address);
+    if fetch(program, program_address, program_address) == Some(HIRES_ENTRY) {
+        return Detection {
+            variant: Variant::HiresChip8,
+            evidence: vec![(program_address, HIRES_ENTRY, Variant::HiresChip8)],
+        };
+    }
+
+    // CHIP-8X programs are loaded higher up, so their jumps only make sense from there. A program
+    // written for 0x200 usually jumps outside itself when read from 0x300, and is then not taken
+    // for CHIP-8X even if the misread code happens to contain 02A0.
+    let chip8x_address = config
+        .program_address
+        .unwrap_or(chip8x::LAYOUT.program_address);
+    let (chip8x_evidence, contained) = walk(program, chip8x_address, true);
+    if contained && !chip8x_evidence.is_empty() {
+        return Detection {
+            variant: Variant::Chip8X,
+            evidence: chip8x_evidence,
+        };
+    }
+
+    let (evidence, _) = walk(program, program_address, false);
+    Detection {
+        variant: evidence
+            .iter()
+            .map(|&(_, _, variant)| variant)
+            .max()
+            .unwrap_or(Variant::Chip8),
+        evidence,
+    }
+}
+
+fn fetch(program: &[u8], program_address: usize, address: usize) -> Option<u16> {
+    let offset = address.checked_sub(program_address)?;
+    let bytes = program.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+// Returns the CHIP-8X opcodes reachable from the program address if `chip8x` is set, or the
+// opcodes of every other extension otherwise, and whether the walk stayed inside the program.
+fn walk(
+    program: &[u8],
+    program_address: usize,
+    chip8x: bool,
+) -> (Vec<(usize, u16, Variant)>, bool) {
+    let fetch = |address: usize| fetch(program, program_address, address);
+
+    let mut visited = BTreeSet::new();
+    let mut pending = vec![program_address];
+    let mut evidence = Vec::new();
+    let mut contained = true;
+
+    while let Some(address) = pending.pop() {
+        if !visited.insert(address) {
+            continue;
+        }
+        let Some(opcode) = fetch(address) else {
+            contained = false;
+            continue;
+        };
+
+        let variant = match opcode_variant(opcode) {
+            Variant::Chip8X if !chip8x => Variant::Chip8,
+            variant if chip8x && variant != Variant::Chip8X => Variant::Chip8,
+            variant => variant,
+        };
+        if variant != Variant::Chip8 {
+            evidence.push((address, opcode, variant));
+        }
+
+        let next = address + opcode_size(opcode);
+
+        // Some XO-CHIP opcodes alias CHIP-8 ones (5XY2 would decode as 5XY0), so none of them
+        // are decoded and they are treated as straight-line code, like MEGA-CHIP's.
+        let instruction = match variant {
+            Variant::XoChip | Variant::MegaChip => None,
+            _ if chip8x => Instruction::new_chip8x(opcode),
+            _ => Instruction::new(opcode),
+        };
+
+        use Instruction::*;
+        match instruction {
+            Some(Jump { address: target }) => pending.push(target),
+            Some(Call { address: target }) => pending.extend([target, next]),
+            Some(Return | Exit | JumpOffset { .. }) => (),
+            Some(
+                SkipEq { .. }
+                | SkipNotEq { .. }
+                | SkipEqLiteral { .. }
+                | SkipNotEqLiteral { .. }
+                | SkipIfKey { .. }
+                | SkipIfNotKey { .. }
+                | SkipIfKeypad2 { .. }
+                | SkipIfNotKeypad2 { .. },
+            ) => {
+                let skipped = next + fetch(next).map_or(2, opcode_size);
+                pending.extend([next, skipped]);
+            }
+            Some(_) => pending.push(next),
+            None if matches!(variant, Variant::XoChip | Variant::MegaChip) => pending.push(next),
+            None => (),
+        }
+    }
+
+    // Without 0011, 04NN to 09NN are more likely to be calls to machine code than MEGA-CHIP.
+    let mega_on = evidence.iter().any(|&(_, opcode, _)| opcode == MEGA_ON);
+    evidence
+        .retain(|&(_, opcode, variant)| mega_on || variant != Variant::MegaChip || opcode < 0x0400);
+    evidence.sort();
+    (evidence, contained)
+}
+
+// XO-CHIP's F000 NNNN and MEGA-CHIP's 01NN NNNN are four bytes long.
+fn opcode_size(opcode: u16) -> usize {
+    match opcode {
+        0xF000 | 0x0100..=0x01FF => 4,
+        _ => 2,
+    }
+}
+
+fn opcode_variant(opcode: u16) -> Variant {
+    let (x, n) = ((opcode & 0x0F00) >> 8, opcode & 0x000F);
+    match opcode & 0xF000 {
+        0x0000 => match opcode {
+            0x00FB..=0x00FF => Variant::SuperChip,
+            CHIP8X_STEP_BACKGROUND => Variant::Chip8X,
+            HIRES_CLEAR_SCREEN => Variant::Chip8,
+            MEGA_ON | 0x0100..=0x09FF => Variant::MegaChip,
+            _ if opcode & 0xFFF0 == 0x00C0 => Variant::SuperChip,
+            _ if opcode & 0xFFF0 == 0x00D0 => Variant::XoChip,
+            _ => Variant::Chip8,
+        },
+        0x5000 if n == 0x2 || n == 0x3 => Variant::XoChip,
+        0xD000 if n == 0x0 => Variant::SuperChip,
+        0xF000 => match opcode & 0x00FF {
+            0x00 if x == 0 => Variant::XoChip,
+            0x01 | 0x3A => Variant::XoChip,
+            0x02 if x == 0 => Variant::XoChip,
+            0x30 | 0x75 | 0x85 => Variant::SuperChip,
+            _ => Variant::Chip8,
+        },
+        _ => Variant::Chip8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program(opcodes: &[u16]) -> Vec<u8> {
+        opcodes
+            .iter()
+            .flat_map(|opcode| opcode.to_be_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn opcode_variant_recognises_extensions() {
+        assert_eq!(opcode_variant(0x00E0), Variant::Chip8);
+        assert_eq!(opcode_variant(0x6A12), Variant::Chip8);
+        assert_eq!(opcode_variant(0x00FF), Variant::SuperChip);
+        assert_eq!(opcode_variant(0x00C4), Variant::SuperChip);
+        assert_eq!(opcode_variant(0xD120), Variant::SuperChip);
+        assert_eq!(opcode_variant(0xF375), Variant::SuperChip);
+        assert_eq!(opcode_variant(0x5122), Variant::XoChip);
+        assert_eq!(opcode_variant(0xF000), Variant::XoChip);
+        assert_eq!(opcode_variant(0xF23A), Variant::XoChip);
+    }
+
+    #[test]
+    fn detect_finds_hires_entry() {
+        let detection = detect(&program(&[0x1260, 0x00E0]), &MachineConfig::new());
+        assert_eq!(detection.variant, Variant::HiresChip8);
+    }
+
+    #[test]
+    fn detect_only_counts_reachable_opcodes() {
+        let unreachable = program(&[0x1204, 0x00FF, 0x1204]);
+        let detection = detect(&unreachable, &MachineConfig::new());
+        assert_eq!(detection.variant, Variant::Chip8);
+        assert!(detection.evidence.is_empty());
+
+        let reachable = program(&[0x00FF, 0x1202]);
+        let detection = detect(&reachable, &MachineConfig::new());
+        assert_eq!(detection.variant, Variant::SuperChip);
+        assert_eq!(detection.evidence, [(0x200, 0x00FF, Variant::SuperChip)]);
+    }
+
+    #[test]
+    fn detect_follows_calls_and_skips() {
+        // 0x200: call 0x208, 0x202: skip 0x204 if V0 is 1, 0x206: loop, 0x208: return.
+        let program = program(&[0x2208, 0x3001, 0x00FE, 0x1206, 0x00EE]);
+        let detection = detect(&program, &MachineConfig::new());
+        assert_eq!(detection.evidence, [(0x204, 0x00FE, Variant::SuperChip)]);
+    }
+
+    #[test]
+    fn opcode_variant_separates_megachip_from_lookalikes() {
+        assert_eq!(opcode_variant(0x0011), Variant::MegaChip);
+        assert_eq!(opcode_variant(0x0010), Variant::Chip8);
+        assert_eq!(opcode_variant(0x0112), Variant::MegaChip);
+        assert_eq!(opcode_variant(0x0304), Variant::MegaChip);
+        assert_eq!(opcode_variant(0x0230), Variant::Chip8);
+        assert_eq!(opcode_variant(0x02A0), Variant::Chip8X);
+        assert_eq!(opcode_variant(0x5121), Variant::Chip8);
+        assert_eq!(opcode_variant(0xB123), Variant::Chip8);
+    }
+
+    #[test]
+    fn detect_needs_mega_on_for_late_megachip_opcodes() {
+        let mega = program(&[0x0011, 0x0405, 0x0101, 0x0000, 0x1208]);
+        let detection = detect(&mega, &MachineConfig::new());
+        assert_eq!(detection.variant, Variant::MegaChip);
+        assert_eq!(
+            detection.evidence,
+            [
+                (0x200, 0x0011, Variant::MegaChip),
+                (0x202, 0x0405, Variant::MegaChip),
+                (0x204, 0x0101, Variant::MegaChip),
+            ]
+        );
+
+        let machine_code = program(&[0x0405, 0x1202]);
+        let detection = detect(&machine_code, &MachineConfig::new());
+        assert_eq!(detection.variant, Variant::Chip8);
+    }
+
+    #[test]
+    fn detect_walks_chip8x_programs_from_0x300() {
+        let chip8x = program(&[0x6001, 0x1306, 0x00FF, 0x02A0, 0x1308]);
+        let detection = detect(&chip8x, &MachineConfig::new());
+        assert_eq!(detection.variant, Variant::Chip8X);
+        assert_eq!(detection.evidence, [(0x306, 0x02A0, Variant::Chip8X)]);
+    }
+
+    #[test]
+    fn detect_starts_at_configured_program_address() {
+        let program = program(&[0x1404, 0x0000, 0x00FF, 0x1406]);
+        let detection = detect(&program, &MachineConfig::new());
+        assert_eq!(detection.variant, Variant::Chip8);
+
+        let detection = detect(&program, &MachineConfig::new().program_address(0x400));
+        assert_eq!(detection.variant, Variant::SuperChip);
+    }
+
+    #[test]
+    fn detect_keeps_chip8_programs_using_bnnn() {
+        let detection = detect(&program(&[0x6000, 0xB210]), &MachineConfig::new());
+        assert_eq!(detection.variant, Variant::Chip8);
+    }
+
+    #[test]
+    fn detect_ignores_chip8x_opcodes_in_misread_programs() {
+        // From 0x200 this jumps over its data to 0x304. Read from 0x300, it reaches 02A0 and then
+        // jumps back below its start.
+        let mut misread = program(&[0x1304, 0x0000, 0x02A0, 0x1202]);
+        misread.resize(0x104, 0);
+        misread.extend(program(&[0x1304]));
+        let detection = detect(&misread, &MachineConfig::new());
+        assert_eq!(detection.variant, Variant::Chip8);
+    }
+}
@@ -0,0 +1,83 @@
+use super::{
+    chip8::Chip8,
+    instruction::Instruction,
+    machine::{Layout, MachineConfig},
+    ErrorKind, Interpreter, Keypad, Quirks, Snapshot, Variant,
+};
+
+const LAYOUT: Layout = Layout {
+    memory_size: 4096,
+    program_address: 0x200,
+    font_address: 0x50,
+    display_size: (64, 64),
+};
+// Programs are loaded at 0x200 like any other, but begin with a jump into a patch of the
+// interpreter that then starts the program proper at 0x2C0. The patch itself is not emulated.
+const ENTRY_OFFSET: usize = 0xC0;
+
+// The hi-res CHIP-8 interpreter for the COSMAC VIP. Besides its 64x64 display, it only differs from
+// CHIP-8 in 0230, which clears the taller screen.
+pub struct HiresChip8 {
+    chip8: Chip8,
+}
+
+impl HiresChip8 {
+    pub fn new(program: &[u8], config: &MachineConfig) -> Result<Self, ErrorKind> {
+        Ok(Self {
+            chip8: Chip8::with_layout(program, config, LAYOUT, ENTRY_OFFSET)?,
+        })
+    }
+
+    pub fn with_quirks(mut self, quirks: Quirks) -> Self {
+        self.chip8 = self.chip8.with_quirks(quirks);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.chip8 = self.chip8.with_seed(seed);
+        self
+    }
+}
+
+impl Interpreter for HiresChip8 {
+    fn display(&self) -> Vec<&[u8]> {
+        self.chip8.display()
+    }
+
+    fn sound_timer(&self) -> u8 {
+        self.chip8.sound_timer()
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        self.chip8.snapshot()
+    }
+
+    fn variant(&self) -> Variant {
+        Variant::HiresChip8
+    }
+
+    fn pc(&self) -> usize {
+        self.chip8.pc()
+    }
+
+    fn update_timers(&mut self) {
+        self.chip8.update_timers()
+    }
+
+    fn fetch(&mut self) -> Result<u16, ErrorKind> {
+        self.chip8.fetch()
+    }
+
+    fn execute_instruction(
+        &mut self,
+        instruction: Instruction,
+        keypad: &mut Keypad,
+    ) -> Result<(), ErrorKind> {
+        match instruction {
+            Instruction::HiresClearScreen => self
+                .chip8
+                .execute_instruction(Instruction::ClearScreen, keypad),
+            _ => self.chip8.execute_instruction(instruction, keypad),
+        }
+    }
+}
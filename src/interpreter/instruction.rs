@@ -165,6 +165,64 @@ pub enum Instruction {
     },
 
     Exit,
+
+    // Hi-res CHIP-8 extension
+    HiresClearScreen,
+
+    // CHIP-8X extension
+    StepBackground,
+    AddNibbles {
+        x: usize,
+        y: usize,
+    },
+    ColorZones {
+        x: usize,
+        y: usize,
+    },
+    ColorRows {
+        x: usize,
+        y: usize,
+        rows: usize,
+    },
+    SkipIfKeypad2 {
+        key_register: usize,
+    },
+    SkipIfNotKeypad2 {
+        key_register: usize,
+    },
+
+    // MEGA-CHIP extension
+    MegaOff,
+    MegaOn,
+    ScrollUp {
+        amount: usize,
+    },
+    // Followed by the low 16 bits of the address in the next two bytes.
+    SetLongIndex {
+        high: usize,
+    },
+    LoadPalette {
+        colors: usize,
+    },
+    SpriteWidth {
+        width: usize,
+    },
+    SpriteHeight {
+        height: usize,
+    },
+    ScreenAlpha {
+        alpha: u8,
+    },
+    PlaySample {
+        looping: bool,
+    },
+    StopSample,
+    BlendMode {
+        mode: u8,
+    },
+    CollisionColor {
+        color: u8,
+    },
 }
 
 impl Instruction {
@@ -181,6 +239,7 @@ impl Instruction {
                 0x00FB => Some(ScrollRight),
                 0x00FC => Some(ScrollLeft),
                 0x00FD => Some(Exit),
+                0x0230 => Some(HiresClearScreen),
                 _ => match opcode & 0x00F0 {
                     0x00C0 => Some(ScrollDown {
                         amount: xyn(opcode).2 as usize,
@@ -298,6 +357,64 @@ impl Instruction {
     }
 }
 
+impl Instruction {
+    // CHIP-8X reuses 5XY1 and BNNN, so its opcodes are decoded separately.
+    pub fn new_chip8x(opcode: u16) -> Option<Self> {
+        use Instruction::*;
+
+        let (x, y, n) = xyn(opcode);
+        match opcode & 0xF000 {
+            0x0000 if opcode == 0x02A0 => Some(StepBackground),
+            0x5000 if n == 0x1 => Some(AddNibbles { x, y }),
+            0xB000 if n == 0x0 => Some(ColorZones { x, y }),
+            0xB000 => Some(ColorRows {
+                x,
+                y,
+                rows: n as usize,
+            }),
+            0xE000 => match opcode & 0x00FF {
+                0xF2 => Some(SkipIfKeypad2 { key_register: x }),
+                0xF5 => Some(SkipIfNotKeypad2 { key_register: x }),
+                _ => Self::new(opcode),
+            },
+            _ => Self::new(opcode),
+        }
+    }
+}
+
+impl Instruction {
+    // MEGA-CHIP gives a meaning to 0NNN opcodes that other variants leave to machine code.
+    pub fn new_megachip(opcode: u16) -> Option<Self> {
+        use Instruction::*;
+
+        let (_, nn) = xnn(opcode);
+        match opcode & 0xFF00 {
+            0x0000 => match opcode {
+                0x0010 => Some(MegaOff),
+                0x0011 => Some(MegaOn),
+                _ if opcode & 0xFFF0 == 0x00B0 => Some(ScrollUp {
+                    amount: xyn(opcode).2 as usize,
+                }),
+                _ => Self::new(opcode),
+            },
+            0x0100 => Some(SetLongIndex { high: nn as usize }),
+            0x0200 => Some(LoadPalette {
+                colors: nn as usize,
+            }),
+            0x0300 => Some(SpriteWidth { width: nn as usize }),
+            0x0400 => Some(SpriteHeight {
+                height: nn as usize,
+            }),
+            0x0500 => Some(ScreenAlpha { alpha: nn }),
+            0x0600 if nn <= 0x01 => Some(PlaySample { looping: nn == 0 }),
+            0x0700 if nn == 0x00 => Some(StopSample),
+            0x0800 if nn <= 0x0F => Some(BlendMode { mode: nn }),
+            0x0900 => Some(CollisionColor { color: nn }),
+            _ => Self::new(opcode),
+        }
+    }
+}
+
 fn xyn(opcode: u16) -> (usize, usize, u8) {
     (
         ((opcode & 0x0F00) >> 8) as usize,
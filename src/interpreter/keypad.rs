@@ -0,0 +1,197 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct KeyEvent {
+    key: u8,
+    pressed: bool,
+    // Seconds on whatever clock the caller uses, only compared against `Keypad::advance`.
+    time: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Wait {
+    #[default]
+    Idle,
+    Press,
+    Release(u8),
+}
+
+// The state of the 16-key hex keypad as seen by the interpreter. Press and release events are
+// queued and applied in order once their time has come. Every press and release is also latched
+// as an edge until the next `advance`, and consumed at most once, so a key tapped between two
+// frames is not lost and a single release cannot satisfy more than one FX0A.
+#[derive(Debug, Clone, Default)]
+pub struct Keypad {
+    down: [bool; 16],
+    pressed: [bool; 16],
+    released: [bool; 16],
+    events: Vec<KeyEvent>,
+    wait: Wait,
+}
+
+impl Keypad {
+    pub fn press(&mut self, key: u8, time: f64) {
+        self.events.push(KeyEvent {
+            key,
+            pressed: true,
+            time,
+        });
+    }
+
+    pub fn release(&mut self, key: u8, time: f64) {
+        self.events.push(KeyEvent {
+            key,
+            pressed: false,
+            time,
+        });
+    }
+
+    // Turns a snapshot of which keys are down and which were released since the last snapshot
+    // into events. A key that was released without being seen down was tapped in between.
+    pub fn set_state(&mut self, keys_down: &[bool; 16], keys_released: &[bool; 16], time: f64) {
+        let mut down = self.down;
+        for event in &self.events {
+            down[event.key as usize] = event.pressed;
+        }
+
+        for key in 0..16 {
+            if keys_released[key] {
+                if !down[key] {
+                    self.press(key as u8, time);
+                }
+                self.release(key as u8, time);
+                down[key] = false;
+            }
+            if keys_down[key] && !down[key] {
+                self.press(key as u8, time);
+            } else if !keys_down[key] && down[key] {
+                self.release(key as u8, time);
+            }
+        }
+    }
+
+    // Applies every queued event up to and including `time`, dropping edges that were not
+    // consumed since the previous call.
+    pub fn advance(&mut self, time: f64) {
+        self.pressed = [false; 16];
+        self.released = [false; 16];
+
+        self.events.sort_by(|a, b| a.time.total_cmp(&b.time));
+        let due = self.events.partition_point(|event| event.time <= time);
+
+        for event in self.events.drain(..due) {
+            let key = event.key as usize;
+            self.down[key] = event.pressed;
+            match event.pressed {
+                true => {
+                    self.pressed[key] = true;
+                    self.released[key] = false;
+                }
+                false => self.released[key] = true,
+            }
+        }
+    }
+
+    // Returns None for keys that are not on the keypad.
+    pub fn is_down(&self, key: u8) -> Option<bool> {
+        self.down.get(key as usize).copied()
+    }
+
+    // Whether the interpreter is blocked in FX0A.
+    pub fn is_waiting(&self) -> bool {
+        self.wait != Wait::Idle
+    }
+
+    // FX0A as on the COSMAC VIP: wait until a key is down, then until that key is released, and
+    // only then return it. Returns None while still waiting; the instruction is then re-executed.
+    pub fn get_key(&mut self) -> Option<u8> {
+        if self.wait == Wait::Idle {
+            self.wait = Wait::Press;
+        }
+
+        if self.wait == Wait::Press {
+            let key = (0..16).find(|&key| self.down[key] || self.pressed[key]);
+            if let Some(key) = key {
+                self.pressed[key] = false;
+                self.wait = Wait::Release(key as u8);
+            }
+        }
+
+        match self.wait {
+            Wait::Release(key) if self.released[key as usize] || !self.down[key as usize] => {
+                self.released[key as usize] = false;
+                self.wait = Wait::Idle;
+                Some(key)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_key_waits_for_press_then_release() {
+        let mut keypad = Keypad::default();
+        assert_eq!(keypad.get_key(), None);
+
+        keypad.press(0x5, 0.0);
+        keypad.advance(0.0);
+        assert_eq!(keypad.get_key(), None);
+        assert!(keypad.is_waiting());
+
+        keypad.release(0x5, 1.0);
+        keypad.advance(1.0);
+        assert_eq!(keypad.get_key(), Some(0x5));
+        assert!(!keypad.is_waiting());
+    }
+
+    #[test]
+    fn get_key_returns_key_tapped_between_frames() {
+        let mut keypad = Keypad::default();
+        let mut released = [false; 16];
+        released[0xA] = true;
+
+        keypad.set_state(&[false; 16], &released, 0.0);
+        keypad.advance(0.0);
+        assert_eq!(keypad.is_down(0xA), Some(false));
+        assert_eq!(keypad.get_key(), Some(0xA));
+    }
+
+    #[test]
+    fn release_is_consumed_once() {
+        let mut keypad = Keypad::default();
+        keypad.press(0x3, 0.0);
+        keypad.release(0x3, 0.0);
+        keypad.advance(0.0);
+
+        assert_eq!(keypad.get_key(), Some(0x3));
+        assert_eq!(keypad.get_key(), None);
+
+        keypad.advance(1.0);
+        assert_eq!(keypad.get_key(), None);
+    }
+
+    #[test]
+    fn advance_drops_unconsumed_edges() {
+        let mut keypad = Keypad::default();
+        keypad.press(0x7, 0.0);
+        keypad.release(0x7, 0.0);
+        keypad.advance(0.0);
+        keypad.advance(1.0);
+
+        assert_eq!(keypad.get_key(), None);
+    }
+
+    #[test]
+    fn events_wait_for_their_time() {
+        let mut keypad = Keypad::default();
+        keypad.press(0x1, 1.0);
+        keypad.advance(0.5);
+        assert_eq!(keypad.is_down(0x1), Some(false));
+
+        keypad.advance(1.0);
+        assert_eq!(keypad.is_down(0x1), Some(true));
+        assert_eq!(keypad.is_down(0x10), None);
+    }
+}
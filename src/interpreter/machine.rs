@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+
+use super::{font::Font, load_program, stack::StackConfig, ErrorKind};
+
+// MEGA-CHIP's index register is 24 bits wide, so nothing past 16 MiB can be reached.
+pub const MAX_MEMORY_SIZE: usize = 0x1000000;
+
+// Memory layout and display size of the emulated machine. Anything left unset falls back to the
+// variant's default, so the same configuration can be used with every variant.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MachineConfig {
+    pub memory_size: Option<usize>,
+    pub program_address: Option<usize>,
+    pub font_address: Option<usize>,
+    // Width and height in pixels of the highest resolution the variant supports.
+    pub display_size: Option<(usize, usize)>,
+    pub font: Font,
+    pub stack: StackConfig,
+}
+
+impl MachineConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn memory_size(mut self, size: usize) -> Self {
+        self.memory_size = Some(size);
+        self
+    }
+
+    pub fn program_address(mut self, address: usize) -> Self {
+        self.program_address = Some(address);
+        self
+    }
+
+    pub fn font_address(mut self, address: usize) -> Self {
+        self.font_address = Some(address);
+        self
+    }
+
+    pub fn font(mut self, font: Font) -> Self {
+        self.font = font;
+        self
+    }
+
+    pub fn display_size(mut self, width: usize, height: usize) -> Self {
+        self.display_size = Some((width, height));
+        self
+    }
+
+    pub fn stack_depth(mut self, depth: usize) -> Self {
+        self.stack.depth = Some(depth);
+        self
+    }
+
+    // Fills in the defaults of a variant and checks that the result makes sense.
+    pub(super) fn layout(&self, defaults: Layout) -> Result<Layout, ErrorKind> {
+        let layout = Layout {
+            memory_size: self.memory_size.unwrap_or(defaults.memory_size),
+            program_address: self.program_address.unwrap_or(defaults.program_address),
+            font_address: self.font_address.unwrap_or(defaults.font_address),
+            display_size: self.display_size.unwrap_or(defaults.display_size),
+        };
+
+        if layout.memory_size > MAX_MEMORY_SIZE {
+            return Err(ErrorKind::InvalidMachine("memory is larger than 16 MiB"));
+        }
+        if layout.display_size.0 == 0 || layout.display_size.1 == 0 {
+            return Err(ErrorKind::InvalidMachine("display has no pixels"));
+        }
+
+        Ok(layout)
+    }
+}
+
+// A machine configuration with every value filled in.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Layout {
+    pub memory_size: usize,
+    pub program_address: usize,
+    pub font_address: usize,
+    pub display_size: (usize, usize),
+}
+
+impl Layout {
+    // Builds the initial memory with the font and program loaded into it.
+    pub fn memory(&self, font: &[u8], program: &[u8]) -> Result<Vec<u8>, ErrorKind> {
+        let mut memory = vec![0u8; self.memory_size];
+
+        let font_end = self
+            .font_address
+            .checked_add(font.len())
+            .filter(|&end| end <= self.memory_size)
+            .ok_or(ErrorKind::InvalidMachine("font does not fit in memory"))?;
+        memory[self.font_address..font_end].copy_from_slice(font);
+
+        if self.program_address > self.memory_size {
+            return Err(ErrorKind::InvalidMachine(
+                "program starts past the end of memory",
+            ));
+        }
+        load_program(&mut memory, self.program_address, program)?;
+
+        Ok(memory)
+    }
+
+    pub fn display(&self) -> Vec<Vec<u8>> {
+        let (width, height) = self.display_size;
+        vec![vec![0; width]; height]
+    }
+}
@@ -0,0 +1,328 @@
+use super::{
+    check_bounds,
+    instruction::Instruction,
+    machine::{Layout, MachineConfig, MAX_MEMORY_SIZE},
+    superchip::Superchip,
+    ErrorKind, Interpreter, Keypad, Quirks, Sample, Snapshot, Variant,
+};
+
+// MEGA-CHIP programs reach the whole 16 MiB through 01NN NNNN.
+const LAYOUT: Layout = Layout {
+    memory_size: MAX_MEMORY_SIZE,
+    program_address: 0x200,
+    font_address: 0x50,
+    display_size: (256, 192),
+};
+// Outside of MEGA-CHIP mode, programs run on the SUPER-CHIP display.
+const SUPERCHIP_DISPLAY_SIZE: (usize, usize) = (128, 64);
+
+// Digitised sounds start with their sample rate (16 bits), their length (24 bits) and a reserved
+// byte.
+const SAMPLE_HEADER_SIZE: usize = 6;
+
+// Font glyphs are still 1 bit per pixel, and are drawn in the last colour of the palette.
+const GLYPH_COLOR: u8 = 0xFF;
+
+// MEGA-CHIP 8 by Revival Studios. It is SUPER-CHIP with a 256x192 display in 256 colours, on which
+// sprites of any size are drawn with one palette index per pixel and blended with what is already
+// there. Drawing goes to a buffer that is only shown when the screen is cleared.
+pub struct Megachip {
+    superchip: Superchip,
+    mega: bool,
+    quirks: Quirks,
+    // Sprites below the program are the interpreter's fonts.
+    program_address: usize,
+    width: usize,
+    height: usize,
+    // Palette index of each pixel of the buffer, for collisions.
+    indices: Vec<Vec<u8>>,
+    // RGB bytes of each pixel of the buffer.
+    buffer: Vec<Vec<u8>>,
+    // RGB bytes of each pixel of the screen, as of the last clear.
+    screen: Vec<Vec<u8>>,
+    // RGBA colours.
+    palette: [[u8; 4]; 256],
+    sprite_width: usize,
+    sprite_height: usize,
+    blend_mode: u8,
+    screen_alpha: u8,
+    collision_color: u8,
+    sample: Option<Sample>,
+}
+
+impl Megachip {
+    pub fn new(program: &[u8], config: &MachineConfig) -> Result<Self, ErrorKind> {
+        let layout = config.layout(LAYOUT)?;
+        let (width, height) = layout.display_size;
+        let superchip_config = config
+            .clone()
+            .display_size(SUPERCHIP_DISPLAY_SIZE.0, SUPERCHIP_DISPLAY_SIZE.1);
+
+        // Colours the program has not loaded are opaque white, apart from the transparent first.
+        let mut palette = [[0xFF; 4]; 256];
+        palette[0] = [0; 4];
+
+        Ok(Self {
+            superchip: Superchip::with_layout(program, &superchip_config, LAYOUT)?,
+            mega: false,
+            quirks: Quirks::SUPERCHIP,
+            program_address: layout.program_address,
+            width,
+            height,
+            indices: vec![vec![0; width]; height],
+            buffer: vec![vec![0; width * 3]; height],
+            screen: vec![vec![0; width * 3]; height],
+            palette,
+            sprite_width: 0,
+            sprite_height: 0,
+            blend_mode: 0,
+            screen_alpha: 0xFF,
+            collision_color: 1,
+            sample: None,
+        })
+    }
+
+    pub fn with_quirks(mut self, quirks: Quirks) -> Self {
+        self.superchip = self.superchip.with_quirks(quirks);
+        self.quirks = quirks;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.superchip = self.superchip.with_seed(seed);
+        self
+    }
+
+    // Shows the buffer, faded by the screen alpha, and starts the next one from scratch.
+    fn present(&mut self) {
+        for (screen, buffer) in self.screen.iter_mut().zip(&self.buffer) {
+            for (pixel, &value) in screen.iter_mut().zip(buffer) {
+                *pixel = (value as u16 * self.screen_alpha as u16 / 0xFF) as u8;
+            }
+        }
+        self.indices.iter_mut().for_each(|row| row.fill(0));
+        self.buffer.iter_mut().for_each(|row| row.fill(0));
+    }
+
+    // Moves the buffer by whole pixels, leaving empty pixels behind.
+    fn scroll(&mut self, right: isize, down: isize) {
+        for (rows, bytes_per_pixel) in [(&mut self.indices, 1), (&mut self.buffer, 3)] {
+            let empty_row = vec![0; rows[0].len()];
+            shift(rows, down, empty_row);
+            for row in rows.iter_mut() {
+                shift(row, right * bytes_per_pixel, 0);
+            }
+        }
+    }
+
+    // Sets a pixel of the buffer to a colour of the palette, and returns whether it collided.
+    fn draw_pixel(&mut self, x: usize, y: usize, color: u8) -> bool {
+        if !self.quirks.wrap && (x >= self.width || y >= self.height) {
+            return false;
+        }
+
+        let (x, y) = (x % self.width, y % self.height);
+        let collided = self.indices[y][x] == self.collision_color;
+        self.indices[y][x] = color;
+
+        let [r, g, b, a] = self.palette[color as usize];
+        let alpha = a as u32
+            * match self.blend_mode {
+                1 => 0x40,
+                2 => 0x80,
+                3 => 0xC0,
+                _ => 0x100,
+            }
+            / 0x100;
+        for (channel, source) in self.buffer[y][x * 3..x * 3 + 3].iter_mut().zip([r, g, b]) {
+            let (source, destination) = (source as u32, *channel as u32);
+            let blended = match self.blend_mode {
+                4 => (destination + source).min(0xFF),
+                5 => destination * source / 0xFF,
+                _ => source,
+            };
+            *channel = ((blended * alpha + destination * (0xFF - alpha)) / 0xFF) as u8;
+        }
+
+        collided
+    }
+
+    fn draw(
+        &mut self,
+        x: usize,
+        y: usize,
+        rows: usize,
+        instruction: Instruction,
+    ) -> Result<(), ErrorKind> {
+        let index = *self.superchip.index_mut();
+        let registers = *self.superchip.registers_mut();
+        let (x, y) = (registers[x] as usize, registers[y] as usize);
+
+        let (width, height, glyph) = match index < self.program_address {
+            true => (1, rows, true),
+            false => (self.sprite_width, self.sprite_height, false),
+        };
+        check_bounds(self.superchip.memory(), index, width * height, instruction)?;
+        self.superchip.registers_mut()[0xF] = 0;
+        // Nothing is drawn until the program sets the size of its sprites.
+        if width * height == 0 {
+            return Ok(());
+        }
+        let sprite = self.superchip.memory()[index..index + width * height].to_vec();
+
+        let mut collided = false;
+        for (y_offset, row) in sprite.chunks(width).enumerate() {
+            if glyph {
+                for x_offset in 0..8 {
+                    if (row[0] >> (7 - x_offset)) & 1 == 1 {
+                        collided |= self.draw_pixel(x + x_offset, y + y_offset, GLYPH_COLOR);
+                    }
+                }
+            } else {
+                for (x_offset, &color) in row.iter().enumerate() {
+                    if color != 0 {
+                        collided |= self.draw_pixel(x + x_offset, y + y_offset, color);
+                    }
+                }
+            }
+        }
+
+        self.superchip.registers_mut()[0xF] = collided as u8;
+        Ok(())
+    }
+
+    fn load_palette(&mut self, colors: usize, instruction: Instruction) -> Result<(), ErrorKind> {
+        let index = *self.superchip.index_mut();
+        check_bounds(self.superchip.memory(), index, colors * 4, instruction)?;
+
+        // Colours are stored as ARGB, and fill the palette from its second entry.
+        let memory = &self.superchip.memory()[index..index + colors * 4];
+        for (entry, argb) in self.palette[1..].iter_mut().zip(memory.chunks(4)) {
+            *entry = [argb[1], argb[2], argb[3], argb[0]];
+        }
+        Ok(())
+    }
+
+    fn play_sample(&mut self, looping: bool, instruction: Instruction) -> Result<(), ErrorKind> {
+        let index = *self.superchip.index_mut();
+        let memory = self.superchip.memory();
+        check_bounds(memory, index, SAMPLE_HEADER_SIZE, instruction)?;
+
+        let header = &memory[index..index + SAMPLE_HEADER_SIZE];
+        let rate = u16::from_be_bytes([header[0], header[1]]) as u32;
+        let length = u32::from_be_bytes([0, header[2], header[3], header[4]]) as usize;
+        let start = index + SAMPLE_HEADER_SIZE;
+        check_bounds(memory, start, length, instruction)?;
+
+        self.sample = Some(Sample {
+            data: memory[start..start + length].to_vec(),
+            rate,
+            looping,
+            id: self.sample.as_ref().map_or(0, |sample| sample.id + 1),
+        });
+        Ok(())
+    }
+}
+
+// Moves the items of a slice towards its end, or its start if `amount` is negative, and fills
+// the items left behind with `empty`.
+fn shift<T: Clone>(items: &mut [T], amount: isize, empty: T) {
+    let len = items.len();
+    let count = amount.unsigned_abs().min(len);
+    match amount >= 0 {
+        true => {
+            items.rotate_right(count);
+            items[..count].fill(empty);
+        }
+        false => {
+            items.rotate_left(count);
+            items[len - count..].fill(empty);
+        }
+    }
+}
+
+impl Interpreter for Megachip {
+    fn display(&self) -> Vec<&[u8]> {
+        match self.mega {
+            true => self.indices.iter().map(|row| row.as_slice()).collect(),
+            false => self.superchip.display(),
+        }
+    }
+
+    fn sound_timer(&self) -> u8 {
+        self.superchip.sound_timer()
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        self.superchip.snapshot()
+    }
+
+    fn variant(&self) -> Variant {
+        Variant::MegaChip
+    }
+
+    fn pc(&self) -> usize {
+        self.superchip.pc()
+    }
+
+    fn rgb_display(&self) -> Option<Vec<&[u8]>> {
+        self.mega
+            .then(|| self.screen.iter().map(|row| row.as_slice()).collect())
+    }
+
+    fn sample(&self) -> Option<&Sample> {
+        self.sample.as_ref()
+    }
+
+    fn decode(&self, opcode: u16) -> Option<Instruction> {
+        Instruction::new_megachip(opcode)
+    }
+
+    fn update_timers(&mut self) {
+        self.superchip.update_timers()
+    }
+
+    fn fetch(&mut self) -> Result<u16, ErrorKind> {
+        self.superchip.fetch()
+    }
+
+    fn execute_instruction(
+        &mut self,
+        instruction: Instruction,
+        keypad: &mut Keypad,
+    ) -> Result<(), ErrorKind> {
+        use Instruction::*;
+        match instruction {
+            MegaOn => {
+                self.mega = true;
+                self.present();
+            }
+            MegaOff => self.mega = false,
+            SetLongIndex { high } => {
+                let low = self.fetch()? as usize;
+                *self.superchip.index_mut() = (high << 16) | low;
+            }
+            LoadPalette { colors } => self.load_palette(colors, instruction)?,
+            SpriteWidth { width } => self.sprite_width = if width == 0 { 256 } else { width },
+            SpriteHeight { height } => self.sprite_height = if height == 0 { 256 } else { height },
+            ScreenAlpha { alpha } => self.screen_alpha = alpha,
+            PlaySample { looping } => self.play_sample(looping, instruction)?,
+            StopSample => self.sample = None,
+            BlendMode { mode } => self.blend_mode = mode,
+            CollisionColor { color } => self.collision_color = color,
+            ClearScreen if self.mega => self.present(),
+            Draw {
+                x,
+                y,
+                sprite_height,
+            } if self.mega => self.draw(x, y, sprite_height, instruction)?,
+            ScrollUp { amount } if self.mega => self.scroll(0, -(amount as isize)),
+            ScrollDown { amount } if self.mega => self.scroll(0, amount as isize),
+            ScrollLeft if self.mega => self.scroll(-4, 0),
+            ScrollRight if self.mega => self.scroll(4, 0),
+            _ => self.superchip.execute_instruction(instruction, keypad)?,
+        }
+
+        Ok(())
+    }
+}
@@ -1,53 +1,251 @@
 mod chip8;
+mod chip8x;
+mod detect;
+mod font;
+mod hires_chip8;
 mod instruction;
+mod keypad;
+mod machine;
+mod megachip;
+mod quirks;
+mod stack;
 mod superchip;
 
-use instruction::Instruction;
-use std::time;
+use std::{fmt, time};
 use thiserror::Error;
 
 pub use chip8::Chip8;
-pub use superchip::Superchip;
+pub use chip8x::Chip8X;
+pub use detect::{detect, Variant};
+pub use font::{BigFont, Font, SmallFont};
+pub use hires_chip8::HiresChip8;
+pub use instruction::Instruction;
+pub use keypad::Keypad;
+pub use machine::{MachineConfig, MAX_MEMORY_SIZE};
+pub use megachip::Megachip;
+pub use quirks::Quirks;
+pub use superchip::{Superchip, SuperchipVersion};
 
-#[derive(Error, Debug)]
-pub enum InterpreterError {
-    #[error("Unknown opcode {opcode:#06x} at {address:#06x}")]
-    UnknownOpcode { opcode: u16, address: usize },
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    #[error("Unknown opcode")]
+    UnknownOpcode,
 
     #[error("Attempted to pop out of an empty callstack")]
     PopOutOfMain,
 
+    #[error("Call stack overflowed its {depth} levels")]
+    StackOverflow { depth: usize },
+
     #[error("Program counter was incremented beyond maximum memory size")]
     OutOfMemory,
 
+    #[error(
+        "Instruction {instruction:?} accessed memory at {address:#06x}, which is out of bounds"
+    )]
+    MemoryOutOfBounds {
+        address: usize,
+        instruction: Instruction,
+    },
+
+    #[error("Program is {size} bytes long, but only {max} bytes fit in memory")]
+    ProgramTooLarge { size: usize, max: usize },
+
+    #[error("Invalid machine configuration: {0}")]
+    InvalidMachine(&'static str),
+
+    #[error("Instruction {instruction:?} used key {key:#04x}, which is not on the keypad")]
+    InvalidKey { key: u8, instruction: Instruction },
+
     #[error("Instruction {instruction:?} not in CHIP-8 instruction set")]
     Chip8InvalidInstruction { instruction: Instruction },
 
-    #[allow(unused)]
     #[error("Instruction {instruction:?} not in SUPERCHIP instruction set")]
     SuperchipInvalidInstruction { instruction: Instruction },
 }
 
+// An error raised while running a program, along with where it happened.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub struct InterpreterError {
+    pub kind: ErrorKind,
+    // Address of the instruction that failed.
+    pub address: usize,
+    // None if the instruction could not be fetched.
+    pub opcode: Option<u16>,
+    pub variant: Variant,
+}
+
+impl fmt::Display for InterpreterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.opcode {
+            Some(opcode) => write!(
+                f,
+                "{} at {:#06x} (opcode {:04X}, {:?})",
+                self.kind, self.address, opcode, self.variant
+            ),
+            None => write!(
+                f,
+                "{} at {:#06x} ({:?})",
+                self.kind, self.address, self.variant
+            ),
+        }
+    }
+}
+
+// Checks that `len` bytes starting at `address` are in memory before `instruction` accesses them.
+fn check_bounds(
+    memory: &[u8],
+    address: usize,
+    len: usize,
+    instruction: Instruction,
+) -> Result<(), ErrorKind> {
+    match address.checked_add(len) {
+        Some(end) if end <= memory.len() => Ok(()),
+        _ => Err(ErrorKind::MemoryOutOfBounds {
+            address: address.max(memory.len()),
+            instruction,
+        }),
+    }
+}
+
+// Copies `program` into memory at `address`.
+fn load_program(memory: &mut [u8], address: usize, program: &[u8]) -> Result<(), ErrorKind> {
+    let max = memory.len() - address;
+    if program.len() > max {
+        return Err(ErrorKind::ProgramTooLarge {
+            size: program.len(),
+            max,
+        });
+    }
+
+    memory[address..address + program.len()].copy_from_slice(program);
+    Ok(())
+}
+
+// A copy of the machine state, for inspecting it after a fault.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub pc: usize,
+    pub index: usize,
+    pub registers: [u8; 16],
+    // Return addresses, from the outermost call to the innermost.
+    pub stack: Vec<usize>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    // Copied regions of memory, as (start address, bytes) pairs in address order.
+    pub memory: Vec<(usize, Vec<u8>)>,
+}
+
+// Memory up to this size is copied whole into snapshots.
+const SNAPSHOT_MEMORY_SIZE: usize = 0x10000;
+// Bytes copied on either side of the program counter and the index register otherwise.
+const SNAPSHOT_WINDOW: usize = 0x100;
+
+// Copies the memory for a snapshot. MEGA-CHIP's 16 MiB are too much to copy on every fault, so
+// large memories only keep the parts around the addresses in use.
+fn snapshot_memory(memory: &[u8], pc: usize, index: usize) -> Vec<(usize, Vec<u8>)> {
+    if memory.len() <= SNAPSHOT_MEMORY_SIZE {
+        return vec![(0, memory.to_vec())];
+    }
+
+    let mut windows = [pc, index].map(|address| {
+        let start = (address.saturating_sub(SNAPSHOT_WINDOW) & !0xF).min(memory.len());
+        let end = address.saturating_add(SNAPSHOT_WINDOW).min(memory.len());
+        (start, end)
+    });
+    windows.sort();
+    let [(start, end), (next_start, next_end)] = windows;
+    match next_start <= end {
+        true => vec![(start, memory[start..end.max(next_end)].to_vec())],
+        false => vec![
+            (start, memory[start..end].to_vec()),
+            (next_start, memory[next_start..next_end].to_vec()),
+        ],
+    }
+}
+
+// A digitised sound, as unsigned 8-bit samples.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sample {
+    pub data: Vec<u8>,
+    pub rate: u32,
+    pub looping: bool,
+    // Counts the sounds started, so that starting the same sound again restarts it.
+    pub id: u64,
+}
+
 pub trait Interpreter {
     fn display(&self) -> Vec<&[u8]>;
-    fn is_beeping(&self) -> bool;
+    fn sound_timer(&self) -> u8;
+    fn snapshot(&self) -> Snapshot;
+    fn variant(&self) -> Variant;
+    fn pc(&self) -> usize;
+
+    // RGB colours of the values in the display, for variants with colour hardware. Monochrome
+    // variants are drawn in the colours of the settings instead.
+    fn colors(&self) -> Option<&[[u8; 3]]> {
+        None
+    }
+
+    // Colour of every pixel as RGB bytes, for variants that blend colours together rather than
+    // index a palette. Drawn instead of `display` when present.
+    fn rgb_display(&self) -> Option<Vec<&[u8]>> {
+        None
+    }
+
+    // Digitised sound playing alongside the buzzer, for variants that have one.
+    fn sample(&self) -> Option<&Sample> {
+        None
+    }
+
+    // Turns an opcode into an instruction, for variants that give some opcodes another meaning.
+    fn decode(&self, opcode: u16) -> Option<Instruction> {
+        Instruction::new(opcode)
+    }
 
     fn update_timers(&mut self);
-    fn next_instruction(&mut self) -> Result<Instruction, InterpreterError>;
+    // Reads the opcode at the program counter and moves the program counter past it.
+    fn fetch(&mut self) -> Result<u16, ErrorKind>;
     fn execute_instruction(
         &mut self,
         instruction: Instruction,
-        keys_pressed: &[bool; 16],
-        keys_released: &[bool; 16],
-    ) -> Result<(), InterpreterError>;
+        keypad: &mut Keypad,
+    ) -> Result<(), ErrorKind>;
 
-    #[cfg(not(target_arch = "wasm32"))]
-    fn tick(
+    // Fetches, decodes and executes a single instruction.
+    fn step(&mut self, keypad: &mut Keypad) -> Result<(), InterpreterError> {
+        let (address, variant) = (self.pc(), self.variant());
+        let error = |kind, opcode| InterpreterError {
+            kind,
+            address,
+            opcode,
+            variant,
+        };
+
+        let opcode = self.fetch().map_err(|kind| error(kind, None))?;
+        let instruction = self
+            .decode(opcode)
+            .ok_or_else(|| error(ErrorKind::UnknownOpcode, Some(opcode)))?;
+        self.execute_instruction(instruction, keypad)
+            .map_err(|kind| error(kind, Some(opcode)))
+    }
+
+    // Runs exactly one 60 Hz frame of `instructions` instructions without any timing, so that
+    // runs with the same inputs and RNG seed are reproducible.
+    fn run_frame(
         &mut self,
-        keys_down: &[bool; 16],
-        keys_released: &[bool; 16],
-        speed: u64,
+        keypad: &mut Keypad,
+        instructions: u64,
     ) -> Result<(), InterpreterError> {
+        self.update_timers();
+        for _ in 0..instructions {
+            self.step(keypad)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn tick(&mut self, keypad: &mut Keypad, speed: u64) -> Result<(), InterpreterError> {
         let timer_cycle_duration = time::Duration::from_nanos(1_000_000_000 / 60);
         let cpu_cycle_duration = time::Duration::from_nanos(1_000_000_000 / speed);
 
@@ -57,8 +255,7 @@ pub trait Interpreter {
         self.update_timers();
 
         'cpu: loop {
-            let next_instruction = self.next_instruction()?;
-            self.execute_instruction(next_instruction, keys_down, keys_released)?;
+            self.step(keypad)?;
 
             let cpu_elapsed = now.elapsed() - total_elapsed;
             total_elapsed += cpu_elapsed;
@@ -81,12 +278,7 @@ pub trait Interpreter {
     // platform. I have tried using wasm_bindgen bindings for JS's Performance.now(), but that
     // makes the app lag heavily for some reason. For now at least it can load roms.
     #[cfg(target_arch = "wasm32")]
-    fn tick(
-        &mut self,
-        keys_down: &[bool; 16],
-        keys_released: &[bool; 16],
-        speed: u64,
-    ) -> Result<(), InterpreterError> {
+    fn tick(&mut self, keypad: &mut Keypad, speed: u64) -> Result<(), InterpreterError> {
         let timer_cycle_duration = time::Duration::from_nanos(1_000_000_000 / 60);
         let cpu_cycle_duration = time::Duration::from_nanos(1_000_000_000 / speed);
 
@@ -96,8 +288,7 @@ pub trait Interpreter {
         self.update_timers();
 
         'cpu: loop {
-            let next_instruction = self.next_instruction()?;
-            self.execute_instruction(next_instruction, keys_down, keys_released)?;
+            self.step(keypad)?;
 
             let now = time::Duration::from_millis((eframe::web::now_sec() * 1000.0) as u64);
             let cpu_elapsed = now - then - total_elapsed;
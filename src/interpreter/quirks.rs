@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+// Field names follow the quirk names used by the CHIP-8 program database
+// (https://github.com/chip-8/chip-8-database), so entries can be deserialized directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Quirks {
+    // 8XY6/8XYE shift VX in place instead of shifting VY into VX.
+    pub shift: bool,
+    // FX55/FX65 increment I by X instead of X + 1.
+    pub memory_increment_by_x: bool,
+    // FX55/FX65 leave I unchanged.
+    pub memory_leave_i_unchanged: bool,
+    // Sprites wrap around the edges of the display instead of being clipped.
+    pub wrap: bool,
+    // BNNN behaves as BXNN, jumping to XNN + VX.
+    pub jump: bool,
+    // 8XY1/8XY2/8XY3 reset VF to 0.
+    pub logic: bool,
+}
+
+impl Quirks {
+    pub const CHIP8: Self = Self {
+        shift: false,
+        memory_increment_by_x: false,
+        memory_leave_i_unchanged: true,
+        wrap: false,
+        jump: false,
+        logic: false,
+    };
+
+    pub const SUPERCHIP: Self = Self {
+        shift: true,
+        memory_increment_by_x: false,
+        memory_leave_i_unchanged: true,
+        wrap: false,
+        jump: true,
+        logic: false,
+    };
+
+    // SUPER-CHIP 1.0 still moves I on FX55/FX65, but only by X.
+    pub const SUPERCHIP_1_0: Self = Self {
+        memory_increment_by_x: true,
+        memory_leave_i_unchanged: false,
+        ..Self::SUPERCHIP
+    };
+
+    pub fn index_increment(&self, registers: usize) -> usize {
+        match (self.memory_leave_i_unchanged, self.memory_increment_by_x) {
+            (true, _) => 0,
+            (false, true) => registers,
+            (false, false) => registers + 1,
+        }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self::CHIP8
+    }
+}
@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+
+use super::ErrorKind;
+
+// The COSMAC VIP interpreter keeps its stack in the 48 bytes below 0xED0, growing downwards.
+const VIP_STACK_TOP: usize = 0xED0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StackConfig {
+    // Maximum number of nested calls, or None for the variant's default.
+    pub depth: Option<usize>,
+    // Store return addresses in emulated memory where the VIP does, so that ROMs which read or
+    // overwrite the stack behave as they did on hardware.
+    pub in_memory: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Stack {
+    depth: usize,
+    storage: Storage,
+}
+
+#[derive(Debug, Clone)]
+enum Storage {
+    Host(Vec<usize>),
+    // Address of the most recently pushed entry.
+    Memory { pointer: usize },
+}
+
+impl Stack {
+    pub fn new(config: StackConfig, default_depth: usize) -> Self {
+        Self {
+            depth: config.depth.unwrap_or(default_depth),
+            storage: match config.in_memory {
+                true => Storage::Memory {
+                    pointer: VIP_STACK_TOP,
+                },
+                false => Storage::Host(Vec::new()),
+            },
+        }
+    }
+
+    fn len(&self) -> usize {
+        match &self.storage {
+            Storage::Host(stack) => stack.len(),
+            Storage::Memory { pointer } => (VIP_STACK_TOP - pointer) / 2,
+        }
+    }
+
+    pub fn entries(&self, memory: &[u8]) -> Vec<usize> {
+        match &self.storage {
+            Storage::Host(stack) => stack.clone(),
+            Storage::Memory { pointer } => memory
+                .get(*pointer..VIP_STACK_TOP)
+                .unwrap_or_default()
+                .chunks_exact(2)
+                .rev()
+                .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]) as usize)
+                .collect(),
+        }
+    }
+
+    pub fn push(&mut self, memory: &mut [u8], address: usize) -> Result<(), ErrorKind> {
+        if self.len() >= self.depth {
+            return Err(ErrorKind::StackOverflow { depth: self.depth });
+        }
+
+        match &mut self.storage {
+            Storage::Host(stack) => stack.push(address),
+            Storage::Memory { pointer } => {
+                // Running out of memory for the stack is an overflow all the same.
+                let Some(next) = pointer
+                    .checked_sub(2)
+                    .filter(|next| next + 2 <= memory.len())
+                else {
+                    return Err(ErrorKind::StackOverflow { depth: self.depth });
+                };
+                memory[next..next + 2].copy_from_slice(&(address as u16).to_be_bytes());
+                *pointer = next;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn pop(&mut self, memory: &[u8]) -> Result<usize, ErrorKind> {
+        match &mut self.storage {
+            Storage::Host(stack) => stack.pop().ok_or(ErrorKind::PopOutOfMain),
+            Storage::Memory { pointer } => {
+                if *pointer >= VIP_STACK_TOP {
+                    return Err(ErrorKind::PopOutOfMain);
+                }
+                let bytes = memory
+                    .get(*pointer..*pointer + 2)
+                    .ok_or(ErrorKind::PopOutOfMain)?;
+                *pointer += 2;
+                Ok(u16::from_be_bytes([bytes[0], bytes[1]]) as usize)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_overflows_past_depth() {
+        let mut memory = vec![0; 4096];
+        let mut stack = Stack::new(StackConfig::default(), 2);
+
+        stack.push(&mut memory, 0x202).unwrap();
+        stack.push(&mut memory, 0x204).unwrap();
+        assert_eq!(
+            stack.push(&mut memory, 0x206),
+            Err(ErrorKind::StackOverflow { depth: 2 })
+        );
+        assert_eq!(stack.entries(&memory), [0x202, 0x204]);
+    }
+
+    #[test]
+    fn pop_fails_on_empty_stack() {
+        let memory = vec![0; 4096];
+        let mut stack = Stack::new(StackConfig::default(), 12);
+        assert_eq!(stack.pop(&memory), Err(ErrorKind::PopOutOfMain));
+    }
+
+    #[test]
+    fn in_memory_stack_lives_below_0xed0() {
+        let mut memory = vec![0; 4096];
+        let config = StackConfig {
+            depth: None,
+            in_memory: true,
+        };
+        let mut stack = Stack::new(config, 12);
+
+        stack.push(&mut memory, 0x234).unwrap();
+        stack.push(&mut memory, 0x456).unwrap();
+        assert_eq!(memory[0xECC..0xED0], [0x04, 0x56, 0x02, 0x34]);
+        assert_eq!(stack.entries(&memory), [0x234, 0x456]);
+
+        // Programs that overwrite the stack change where they return to.
+        memory[0xECC..0xECE].copy_from_slice(&[0x03, 0x00]);
+        assert_eq!(stack.pop(&memory), Ok(0x300));
+        assert_eq!(stack.pop(&memory), Ok(0x234));
+        assert_eq!(stack.pop(&memory), Err(ErrorKind::PopOutOfMain));
+    }
+
+    #[test]
+    fn in_memory_stack_overflows_when_memory_runs_out() {
+        let mut memory = vec![0; 0xECE];
+        let config = StackConfig {
+            depth: Some(4),
+            in_memory: true,
+        };
+        let mut stack = Stack::new(config, 12);
+
+        assert_eq!(
+            stack.push(&mut memory, 0x200),
+            Err(ErrorKind::StackOverflow { depth: 4 })
+        );
+    }
+}
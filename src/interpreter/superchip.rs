@@ -1,83 +1,205 @@
-use super::{instruction::Instruction, Interpreter, InterpreterError};
-
-const MEMORY_SIZE: usize = 4096;
-const PROGRAM_ADDRESS: usize = 0x200;
-
-const FONT_ADDRESS: usize = 0x50;
-const BIGFONT_ADDRESS: usize = FONT_ADDRESS + 160;
-
-const FONT_WIDTH: usize = 5;
-const BIGFONT_WIDTH: usize = 10;
-
-const FONT: [u8; 240] = [
-    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-    0x20, 0x60, 0x20, 0x20, 0x70, // 1
-    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
-    0xff, 0xff, 0xc3, 0xc3, 0xc3, 0xc3, 0xc3, 0xc3, 0xff, 0xff, // 0
-    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xff, 0xff, // 1
-    0xff, 0xff, 0x03, 0x03, 0xff, 0xff, 0xc0, 0xc0, 0xff, 0xff, // 2
-    0xff, 0xff, 0x03, 0x03, 0xff, 0xff, 0x03, 0x03, 0xff, 0xff, // 3
-    0xc3, 0xc3, 0xc3, 0xc3, 0xff, 0xff, 0x03, 0x03, 0x03, 0x03, // 4
-    0xff, 0xff, 0xc0, 0xc0, 0xff, 0xff, 0x03, 0x03, 0xff, 0xff, // 5
-    0xff, 0xff, 0xc0, 0xc0, 0xff, 0xff, 0xc3, 0xc3, 0xff, 0xff, // 6
-    0xff, 0xff, 0x03, 0x03, 0x06, 0x0c, 0x18, 0x18, 0x18, 0x18, // 7
-    0xff, 0xff, 0xc3, 0xc3, 0xff, 0xff, 0xc3, 0xc3, 0xff, 0xff, // 8
-    0xff, 0xff, 0xc3, 0xc3, 0xff, 0xff, 0x03, 0x03, 0xff, 0xff, // 9
-    0x7e, 0xff, 0xc3, 0xc3, 0xc3, 0xff, 0xff, 0xc3, 0xc3, 0xc3, // A
-    0xfc, 0xfc, 0xc3, 0xc3, 0xfc, 0xfc, 0xc3, 0xc3, 0xfc, 0xfc, // B
-    0x3c, 0xff, 0xc3, 0xc0, 0xc0, 0xc0, 0xc0, 0xc3, 0xff, 0x3c, // C
-    0xfc, 0xfe, 0xc3, 0xc3, 0xc3, 0xc3, 0xc3, 0xc3, 0xfe, 0xfc, // D
-    0xff, 0xff, 0xc0, 0xc0, 0xff, 0xff, 0xc0, 0xc0, 0xff, 0xff, // E
-    0xff, 0xff, 0xc0, 0xc0, 0xff, 0xff, 0xc0, 0xc0, 0xc0, 0xc0, // F
-];
-
-const DISPLAY_WIDTH: usize = 128;
-const DISPLAY_HEIGHT: usize = 64;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::{
+    check_bounds,
+    font::{BIG_GLYPH_SIZE, SMALL_FONT_SIZE, SMALL_GLYPH_SIZE},
+    instruction::Instruction,
+    machine::{Layout, MachineConfig},
+    snapshot_memory,
+    stack::Stack,
+    ErrorKind, Interpreter, Keypad, Quirks, Snapshot, Variant,
+};
+
+const LAYOUT: Layout = Layout {
+    memory_size: 4096,
+    program_address: 0x200,
+    font_address: 0x50,
+    display_size: (128, 64),
+};
+// SUPER-CHIP allows 16 nested calls.
+const STACK_DEPTH: usize = 16;
+
+// Releases of SUPER-CHIP, where their behaviour differs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuperchipVersion {
+    // SUPER-CHIP 1.0 for the HP 48, which cannot scroll yet.
+    V1_0,
+    // SUPER-CHIP 1.1 for the HP 48. In high resolution, VF counts the sprite rows that collided or
+    // were clipped at the bottom. In low resolution, it scrolls by half a pixel and DXY0 draws an
+    // 8x16 sprite.
+    V1_1,
+    // SUPER-CHIP as Octo runs it, with low resolution pixels handled like whole pixels throughout.
+    Modern,
+}
 
 pub struct Superchip {
-    memory: [u8; MEMORY_SIZE],
+    memory: Vec<u8>,
     pc: usize,
     index: usize,
-    stack: Vec<usize>,
+    stack: Stack,
     delay_timer: u8,
     sound_timer: u8,
     variables: [u8; 16],
+    font_address: usize,
     hires: bool,
-    display: [[u8; DISPLAY_WIDTH]; DISPLAY_HEIGHT],
+    // Always at the high resolution, with each low resolution pixel drawn as a 2x2 block.
+    display: Vec<Vec<u8>>,
     running: bool,
+    quirks: Quirks,
+    version: SuperchipVersion,
+    rng: StdRng,
 }
 
 impl Superchip {
-    pub fn new(program: &[u8]) -> Self {
-        let mut memory = [0u8; MEMORY_SIZE];
-        memory[FONT_ADDRESS..FONT_ADDRESS + FONT.len()].copy_from_slice(&FONT);
-        memory[PROGRAM_ADDRESS..PROGRAM_ADDRESS + program.len()].copy_from_slice(program);
-
-        Self {
-            memory,
-            pc: PROGRAM_ADDRESS,
+    pub fn new(program: &[u8], config: &MachineConfig) -> Result<Self, ErrorKind> {
+        Self::with_layout(program, config, LAYOUT)
+    }
+
+    // Builds the interpreter with another default layout, for variants built on top of SUPER-CHIP.
+    pub(super) fn with_layout(
+        program: &[u8],
+        config: &MachineConfig,
+        defaults: Layout,
+    ) -> Result<Self, ErrorKind> {
+        let layout = config.layout(defaults)?;
+
+        Ok(Self {
+            memory: layout.memory(&config.font.data(true)?, program)?,
+            pc: layout.program_address,
             index: 0,
-            stack: vec![],
+            stack: Stack::new(config.stack, STACK_DEPTH),
             delay_timer: 0,
             sound_timer: 0,
             variables: [0; 16],
+            font_address: layout.font_address,
             hires: false,
-            display: [[0; DISPLAY_WIDTH]; DISPLAY_HEIGHT],
+            display: layout.display(),
             running: true,
+            quirks: Quirks::SUPERCHIP,
+            version: SuperchipVersion::Modern,
+            rng: StdRng::from_entropy(),
+        })
+    }
+
+    pub fn with_quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    pub fn with_version(mut self, version: SuperchipVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    // For variants built on top of SUPER-CHIP with instructions of their own.
+    pub(super) fn registers_mut(&mut self) -> &mut [u8; 16] {
+        &mut self.variables
+    }
+
+    pub(super) fn index_mut(&mut self) -> &mut usize {
+        &mut self.index
+    }
+
+    pub(super) fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+}
+
+impl Superchip {
+    fn width(&self) -> usize {
+        self.display[0].len()
+    }
+
+    fn height(&self) -> usize {
+        self.display.len()
+    }
+
+    // XORs a pixel onto the display, clipping or wrapping it at the edges depending on the quirks,
+    // and returns whether it collided.
+    fn draw_pixel(&mut self, x: usize, y: usize, pixel: u8) -> bool {
+        if !self.quirks.wrap && (x >= self.width() || y >= self.height()) {
+            return false;
+        }
+
+        let (x, y) = (x % self.width(), y % self.height());
+        let collided = self.display[y][x] & pixel == 1;
+        self.display[y][x] ^= pixel;
+        collided
+    }
+
+    // Low resolution pixels are drawn as 2x2 blocks.
+    fn scale(&self) -> usize {
+        match self.hires {
+            true => 1,
+            false => 2,
+        }
+    }
+
+    // How far the display scrolls for a distance in pixels of the current resolution.
+    fn scroll_amount(&self, amount: usize, instruction: Instruction) -> Result<usize, ErrorKind> {
+        match self.version {
+            SuperchipVersion::V1_0 => Err(ErrorKind::SuperchipInvalidInstruction { instruction }),
+            SuperchipVersion::V1_1 => Ok(amount),
+            SuperchipVersion::Modern => Ok(amount * self.scale()),
+        }
+    }
+
+    fn draw(
+        &mut self,
+        x: usize,
+        y: usize,
+        sprite_height: usize,
+        instruction: Instruction,
+    ) -> Result<(), ErrorKind> {
+        let legacy = self.version != SuperchipVersion::Modern;
+        let (sprite_width, sprite_height) = match (sprite_height, self.hires) {
+            (0, false) if legacy => (8, 16),
+            (0, _) => (16, 16),
+            (height, _) => (8, height),
+        };
+        let row_size = sprite_width / 8;
+        check_bounds(
+            &self.memory,
+            self.index,
+            sprite_height * row_size,
+            instruction,
+        )?;
+
+        let scale = self.scale();
+        let x = (self.variables[x] as usize * scale) % self.width();
+        let y = (self.variables[y] as usize * scale) % self.height();
+
+        let (mut collided, mut counted_rows) = (false, 0);
+        for y_offset in 0..sprite_height {
+            let start = self.index + y_offset * row_size;
+            let sprite_row = self.memory[start..start + row_size]
+                .iter()
+                .fold(0u16, |row, &byte| (row << 8) | byte as u16);
+
+            let mut row_collided = false;
+            for x_offset in 0..sprite_width * scale {
+                let pixel = ((sprite_row >> (sprite_width - 1 - x_offset / scale)) & 1) as u8;
+                for y_pixel in 0..scale {
+                    row_collided |=
+                        self.draw_pixel(x + x_offset, y + y_offset * scale + y_pixel, pixel);
+                }
+            }
+
+            let clipped = !self.quirks.wrap && y + y_offset * scale >= self.height();
+            collided |= row_collided;
+            counted_rows += (row_collided || clipped) as u8;
         }
+
+        self.variables[0xF] = match legacy && self.hires {
+            true => counted_rows,
+            false => collided as u8,
+        };
+        Ok(())
     }
 }
 
@@ -86,8 +208,28 @@ impl Interpreter for Superchip {
         self.display.iter().map(|row| row.as_slice()).collect()
     }
 
-    fn is_beeping(&self) -> bool {
-        self.sound_timer > 0
+    fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            pc: self.pc,
+            index: self.index,
+            registers: self.variables,
+            stack: self.stack.entries(&self.memory),
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            memory: snapshot_memory(&self.memory, self.pc, self.index),
+        }
+    }
+
+    fn variant(&self) -> Variant {
+        Variant::SuperChip
+    }
+
+    fn pc(&self) -> usize {
+        self.pc
     }
 
     fn update_timers(&mut self) {
@@ -95,38 +237,37 @@ impl Interpreter for Superchip {
         self.sound_timer = self.sound_timer.saturating_sub(1);
     }
 
-    fn next_instruction(&mut self) -> Result<Instruction, InterpreterError> {
+    fn fetch(&mut self) -> Result<u16, ErrorKind> {
         let opcode = u16::from_be_bytes(
             self.memory
                 .get(self.pc..self.pc + 2)
-                .ok_or(InterpreterError::OutOfMemory)?
+                .ok_or(ErrorKind::OutOfMemory)?
                 .try_into()
                 .expect("Slice should always have length 2"),
         );
 
         self.pc += 2;
-        Instruction::new(opcode).ok_or(InterpreterError::UnknownOpcode {
-            opcode,
-            address: self.pc,
-        })
+        Ok(opcode)
     }
 
     fn execute_instruction(
         &mut self,
         instruction: Instruction,
-        keys_down: &[bool; 16],
-        keys_released: &[bool; 16],
-    ) -> Result<(), InterpreterError> {
+        keypad: &mut Keypad,
+    ) -> Result<(), ErrorKind> {
         use Instruction::*;
         match instruction {
-            ClearScreen => self.display.fill([0; DISPLAY_WIDTH]),
+            ClearScreen => self.display.iter_mut().for_each(|row| row.fill(0)),
             Exit => self.running = false,
             Jump { address } => self.pc = address,
             JumpOffset {
                 address,
                 offset_register,
             } => {
-                self.pc = address + self.variables[offset_register] as usize;
+                let offset_register = if self.quirks.jump { offset_register } else { 0 };
+                let target = address + self.variables[offset_register] as usize;
+                check_bounds(&self.memory, target, 2, instruction)?;
+                self.pc = target;
             }
             SetLiteral { dest, value } => self.variables[dest] = value,
             AddLiteral { dest, value } => {
@@ -136,9 +277,10 @@ impl Interpreter for Superchip {
             SetIndexFont { src, big } => {
                 let character = (self.variables[src] & 0x0F) as usize;
                 if big {
-                    self.index = BIGFONT_ADDRESS + character * BIGFONT_WIDTH;
+                    // The big font is stored right after the small one.
+                    self.index = self.font_address + SMALL_FONT_SIZE + character * BIG_GLYPH_SIZE;
                 } else {
-                    self.index = FONT_ADDRESS + character * FONT_WIDTH;
+                    self.index = self.font_address + character * SMALL_GLYPH_SIZE;
                 }
             }
             AddIndex { src } => {
@@ -147,11 +289,11 @@ impl Interpreter for Superchip {
                 self.variables[0xF] = overflow as u8;
             }
             Call { address } => {
-                self.stack.push(self.pc);
+                self.stack.push(&mut self.memory, self.pc)?;
                 self.pc = address;
             }
             Return => {
-                self.pc = self.stack.pop().ok_or(InterpreterError::PopOutOfMain)?;
+                self.pc = self.stack.pop(&self.memory)?;
             }
             SkipEq { x, y } => {
                 if self.variables[x] == self.variables[y] {
@@ -174,18 +316,26 @@ impl Interpreter for Superchip {
                 }
             }
             SkipIfKey { key_register } => {
-                if keys_down[self.variables[key_register] as usize] {
+                let key = self.variables[key_register];
+                if keypad
+                    .is_down(key)
+                    .ok_or(ErrorKind::InvalidKey { key, instruction })?
+                {
                     self.pc += 2;
                 }
             }
             SkipIfNotKey { key_register } => {
-                if !keys_down[self.variables[key_register] as usize] {
+                let key = self.variables[key_register];
+                if !keypad
+                    .is_down(key)
+                    .ok_or(ErrorKind::InvalidKey { key, instruction })?
+                {
                     self.pc += 2;
                 }
             }
             GetKey { dest } => {
-                if let Some(key) = keys_released.iter().position(|&e| e) {
-                    self.variables[dest] = key as u8;
+                if let Some(key) = keypad.get_key() {
+                    self.variables[dest] = key;
                 } else {
                     self.pc -= 2;
                 }
@@ -195,12 +345,21 @@ impl Interpreter for Superchip {
             }
             Or { lhs, rhs } => {
                 self.variables[lhs] |= self.variables[rhs];
+                if self.quirks.logic {
+                    self.variables[0xF] = 0;
+                }
             }
             And { lhs, rhs } => {
                 self.variables[lhs] &= self.variables[rhs];
+                if self.quirks.logic {
+                    self.variables[0xF] = 0;
+                }
             }
             Xor { lhs, rhs } => {
                 self.variables[lhs] ^= self.variables[rhs];
+                if self.quirks.logic {
+                    self.variables[0xF] = 0;
+                }
             }
             Add { lhs, rhs } => {
                 let (res, overflow) = self.variables[lhs].overflowing_add(self.variables[rhs]);
@@ -212,12 +371,18 @@ impl Interpreter for Superchip {
                 self.variables[dest] = res;
                 self.variables[0xF] = !overflow as u8;
             }
-            LeftShift { lhs, rhs: _ } => {
+            LeftShift { lhs, rhs } => {
+                if !self.quirks.shift {
+                    self.variables[lhs] = self.variables[rhs];
+                }
                 let flag = self.variables[lhs] >> 7;
                 self.variables[lhs] <<= 1;
                 self.variables[0xF] = flag;
             }
-            RightShift { lhs, rhs: _ } => {
+            RightShift { lhs, rhs } => {
+                if !self.quirks.shift {
+                    self.variables[lhs] = self.variables[rhs];
+                }
                 let flag = self.variables[lhs] & 1;
                 self.variables[lhs] >>= 1;
                 self.variables[0xF] = flag;
@@ -237,74 +402,9 @@ impl Interpreter for Superchip {
                 x,
                 y,
                 sprite_height,
-            } => {
-                if self.hires {
-                    let x = self.variables[x] as usize % DISPLAY_WIDTH;
-                    let y = self.variables[y] as usize % DISPLAY_HEIGHT;
-                    self.variables[0xF] = 0;
-
-                    if sprite_height == 0 {
-                        for y_offset in 0..16 {
-                            if y + y_offset >= DISPLAY_HEIGHT {
-                                break;
-                            }
-                            let sprite_row = u16::from_be_bytes([
-                                self.memory[self.index + 2 * y_offset],
-                                self.memory[self.index + 2 * y_offset + 1],
-                            ]);
-                            for x_offset in 0..16 {
-                                if x + x_offset >= DISPLAY_WIDTH {
-                                    break;
-                                }
-                                let pixel = ((sprite_row >> (15 - x_offset)) & 1) as u8;
-                                self.variables[0xF] |=
-                                    self.display[y + y_offset][x + x_offset] & pixel;
-                                self.display[y + y_offset][x + x_offset] ^= pixel;
-                            }
-                        }
-                    } else {
-                        for y_offset in 0..sprite_height {
-                            if y + y_offset >= DISPLAY_HEIGHT {
-                                break;
-                            }
-                            let sprite_row = self.memory[self.index + y_offset];
-                            for x_offset in 0..8 {
-                                if x + x_offset >= DISPLAY_WIDTH {
-                                    break;
-                                }
-                                let pixel = (sprite_row >> (7 - x_offset)) & 1;
-                                self.variables[0xF] |=
-                                    self.display[y + y_offset][x + x_offset] & pixel;
-                                self.display[y + y_offset][x + x_offset] ^= pixel;
-                            }
-                        }
-                    }
-                } else if !self.hires {
-                    let x = (self.variables[x] as usize * 2) % DISPLAY_WIDTH;
-                    let y = (self.variables[y] as usize * 2) % DISPLAY_HEIGHT;
-                    self.variables[0xF] = 0;
-
-                    for y_offset in 0..sprite_height * 2 {
-                        if y + y_offset >= DISPLAY_HEIGHT {
-                            break;
-                        }
-                        let sprite_row = self.memory[self.index + y_offset / 2];
-                        for x_offset in 0..16 {
-                            if x + x_offset >= DISPLAY_WIDTH {
-                                break;
-                            }
-                            let pixel = (sprite_row >> (7 - x_offset / 2)) & 1;
-                            self.variables[0xF] |= self.display[y + y_offset][x + x_offset] & pixel;
-                            self.display[y + y_offset][x + x_offset] ^= pixel;
-                        }
-                    }
-                }
-            }
+            } => self.draw(x, y, sprite_height, instruction)?,
             ScrollRight => {
-                let amount = match self.hires {
-                    true => 4,
-                    false => 2 * 4,
-                };
+                let amount = self.scroll_amount(4, instruction)?.min(self.width());
 
                 self.display.iter_mut().for_each(|row| {
                     row.rotate_right(amount);
@@ -312,28 +412,26 @@ impl Interpreter for Superchip {
                 });
             }
             ScrollLeft => {
-                let amount = match self.hires {
-                    true => 4,
-                    false => 2 * 4,
-                };
+                let amount = self.scroll_amount(4, instruction)?.min(self.width());
 
                 self.display.iter_mut().for_each(|row| {
+                    let width = row.len();
                     row.rotate_left(amount);
-                    row[DISPLAY_WIDTH - amount..].fill(0);
+                    row[width - amount..].fill(0);
                 });
             }
             ScrollDown { amount } => {
-                let amount = match self.hires {
-                    true => amount,
-                    false => 2 * amount,
-                };
+                let amount = self.scroll_amount(amount, instruction)?.min(self.height());
                 self.display.rotate_right(amount);
-                self.display[0..amount].fill([0; DISPLAY_WIDTH]);
+                self.display[0..amount]
+                    .iter_mut()
+                    .for_each(|row| row.fill(0));
             }
             Random { x, mask } => {
-                self.variables[x] = rand::random::<u8>() & mask;
+                self.variables[x] = self.rng.gen::<u8>() & mask;
             }
             DecimalConversion { src } => {
+                check_bounds(&self.memory, self.index, 3, instruction)?;
                 let mut n = self.variables[src];
 
                 for i in (0..3).rev() {
@@ -342,17 +440,42 @@ impl Interpreter for Superchip {
                 }
             }
             StoreMemory { registers } => {
+                check_bounds(&self.memory, self.index, registers + 1, instruction)?;
                 for i in 0..=registers {
                     self.memory[self.index + i] = self.variables[i];
                 }
+                self.index += self.quirks.index_increment(registers);
             }
             LoadMemory { registers } => {
+                check_bounds(&self.memory, self.index, registers + 1, instruction)?;
                 for i in 0..=registers {
                     self.variables[i] = self.memory[self.index + i];
                 }
+                self.index += self.quirks.index_increment(registers);
             }
             SaveFlags { x: _ } => (),
             LoadFlags { x: _ } => (),
+            HiresClearScreen
+            | StepBackground
+            | AddNibbles { .. }
+            | ColorZones { .. }
+            | ColorRows { .. }
+            | SkipIfKeypad2 { .. }
+            | SkipIfNotKeypad2 { .. }
+            | MegaOff
+            | MegaOn
+            | ScrollUp { .. }
+            | SetLongIndex { .. }
+            | LoadPalette { .. }
+            | SpriteWidth { .. }
+            | SpriteHeight { .. }
+            | ScreenAlpha { .. }
+            | PlaySample { .. }
+            | StopSample
+            | BlendMode { .. }
+            | CollisionColor { .. } => {
+                return Err(ErrorKind::SuperchipInvalidInstruction { instruction });
+            }
         }
 
         Ok(())
@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Preset {
+    Qwerty,
+    Azerty,
+    Qwertz,
+    // Keys 0-9 on the digit keys of the same value, rather than on the physical layout. This is
+    // also the numpad preset: egui reports numpad digits as the same `Num0`..`Num9` keys as the
+    // digit row (and numpad Enter, / and - as the main keys), so no preset can bind the numpad
+    // alone.
+    Digits,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 4] = [
+        Preset::Qwerty,
+        Preset::Azerty,
+        Preset::Qwertz,
+        Preset::Digits,
+    ];
+}
+
+// The physical layout of the COSMAC VIP keypad, row by row.
+pub const VIP_LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+// Keys are listed in hex key order (0x0..=0xF). The first three presets follow the physical
+// layout of the COSMAC VIP keypad (see `VIP_LAYOUT`). AZERTY's digit row needs Shift to type
+// digits, and egui reports nothing for it otherwise, so that preset starts on the letter rows and
+// its bottom row carries on along the bottom letter row.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Keymap {
+    bindings: [Vec<egui::Key>; 16],
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::from_preset(Preset::Qwerty)
+    }
+}
+
+impl Keymap {
+    pub fn from_preset(preset: Preset) -> Self {
+        use egui::Key::*;
+        let keys = match preset {
+            Preset::Qwerty => [X, Num1, Num2, Num3, Q, W, E, A, S, D, Z, C, Num4, R, F, V],
+            Preset::Azerty => [N, A, Z, E, Q, S, D, W, X, C, B, Comma, R, F, V, Semicolon],
+            Preset::Qwertz => [X, Num1, Num2, Num3, Q, W, E, A, S, D, Y, C, Num4, R, F, V],
+            Preset::Digits => [
+                Num0, Num1, Num2, Num3, Num4, Num5, Num6, Num7, Num8, Num9, Slash, Plus, Minus,
+                Enter, Period, Backspace,
+            ],
+        };
+
+        Self {
+            bindings: keys.map(|key| vec![key]),
+        }
+    }
+
+    pub fn bindings(&self, key: u8) -> &[egui::Key] {
+        &self.bindings[key as usize]
+    }
+
+    pub fn bind(&mut self, key: u8, egui_key: egui::Key) {
+        let bindings = &mut self.bindings[key as usize];
+        if !bindings.contains(&egui_key) {
+            bindings.push(egui_key);
+        }
+    }
+
+    pub fn unbind(&mut self, key: u8, egui_key: egui::Key) {
+        self.bindings[key as usize].retain(|&k| k != egui_key);
+    }
+
+    pub fn keys_down(&self, input: &egui::InputState) -> [bool; 16] {
+        std::array::from_fn(|key| self.bindings[key].iter().any(|&k| input.key_down(k)))
+    }
+
+    pub fn keys_released(&self, input: &egui::InputState) -> [bool; 16] {
+        std::array::from_fn(|key| self.bindings[key].iter().any(|&k| input.key_released(k)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_bind_each_key_once() {
+        for preset in Preset::ALL {
+            let keymap = Keymap::from_preset(preset);
+            let mut keys = (0..16)
+                .flat_map(|key| keymap.bindings(key).to_vec())
+                .collect::<Vec<egui::Key>>();
+            keys.sort();
+            keys.dedup();
+            assert_eq!(keys.len(), 16, "{:?}", preset);
+        }
+    }
+
+    #[test]
+    fn azerty_avoids_the_digit_row() {
+        let keymap = Keymap::from_preset(Preset::Azerty);
+        let row = VIP_LAYOUT[0].map(|key| keymap.bindings(key)[0]);
+        assert_eq!(
+            row,
+            [egui::Key::A, egui::Key::Z, egui::Key::E, egui::Key::R]
+        );
+    }
+}
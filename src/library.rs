@@ -0,0 +1,109 @@
+use log::warn;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const EXTENSIONS: [&str; 3] = ["ch8", "sc8", "xo8"];
+const DESCRIPTION_EXTENSION: &str = "txt";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub path: PathBuf,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Library {
+    pub root: PathBuf,
+    // Entries grouped by their folder, relative to `root`.
+    pub groups: BTreeMap<PathBuf, Vec<Entry>>,
+}
+
+impl Library {
+    pub fn scan(root: impl AsRef<Path>) -> io::Result<Self> {
+        let root = root.as_ref();
+        let mut library = Self {
+            root: root.to_path_buf(),
+            groups: BTreeMap::new(),
+        };
+
+        let mut visited = HashSet::new();
+        let mut directories = vec![root.to_path_buf()];
+        while let Some(directory) = directories.pop() {
+            // Only a missing or unreadable root fails the scan; anything below it is skipped.
+            let entries = match read_directory(&directory, &mut visited) {
+                Ok(Some(entries)) => entries,
+                Ok(None) => continue,
+                Err(e) if directory == root => return Err(e),
+                Err(e) => {
+                    warn!("Skipping {}: {}", directory.display(), e);
+                    continue;
+                }
+            };
+
+            for entry in entries {
+                let path = match entry {
+                    Ok(entry) => entry.path(),
+                    Err(e) => {
+                        warn!("Skipping an entry of {}: {}", directory.display(), e);
+                        continue;
+                    }
+                };
+                if path.is_dir() {
+                    directories.push(path);
+                } else if is_rom(&path) {
+                    let group = directory
+                        .strip_prefix(root)
+                        .unwrap_or(&directory)
+                        .to_path_buf();
+
+                    library.groups.entry(group).or_default().push(Entry {
+                        name: path
+                            .file_name()
+                            .map(|f| f.to_string_lossy().to_string())
+                            .unwrap_or_default(),
+                        description: fs::read_to_string(path.with_extension(DESCRIPTION_EXTENSION))
+                            .ok(),
+                        path,
+                    });
+                }
+            }
+        }
+
+        library
+            .groups
+            .values_mut()
+            .for_each(|entries| entries.sort_by(|a, b| a.name.cmp(&b.name)));
+
+        Ok(library)
+    }
+
+    pub fn len(&self) -> usize {
+        self.groups.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+}
+
+// Symlinks can lead back to a directory that was already scanned, so each directory is only read
+// once. Returns `None` for directories that were already read.
+fn read_directory(
+    directory: &Path,
+    visited: &mut HashSet<PathBuf>,
+) -> io::Result<Option<fs::ReadDir>> {
+    match visited.insert(fs::canonicalize(directory)?) {
+        true => fs::read_dir(directory).map(Some),
+        false => Ok(None),
+    }
+}
+
+fn is_rom(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
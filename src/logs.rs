@@ -0,0 +1,135 @@
+use egui_logger::EguiLogger;
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+use std::sync::Mutex;
+
+// How many repeats of a message go by between updates of the count, about once a second for a
+// message logged every frame.
+const REPEAT_INTERVAL: usize = 60;
+
+// The last message passed on to the Logs tab.
+static LAST: Mutex<Option<Repeat>> = Mutex::new(None);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Repeat {
+    level: Level,
+    message: String,
+    // How many times the message was logged in a row, and the count last shown in the Logs tab.
+    count: usize,
+    shown: usize,
+}
+
+// Passes log messages on to egui_logger, keeping only the first of a message repeated back to
+// back, such as an interpreter error hit on every frame. While the message repeats, its count is
+// logged every `REPEAT_INTERVAL` repeats, and once more when another message comes in.
+pub struct Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let Ok(mut last) = LAST.lock() else {
+            return;
+        };
+
+        let message = record.args().to_string();
+        for (level, count) in update(&mut last, record.level(), message) {
+            match count {
+                Some(count) => EguiLogger.log(
+                    &Record::builder()
+                        .level(level)
+                        .args(format_args!("(Repeated {} times.)", count))
+                        .build(),
+                ),
+                None => EguiLogger.log(record),
+            }
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+// Records a logged message and returns the lines to show for it: repeat counts, or `None` for the
+// message itself.
+fn update(last: &mut Option<Repeat>, level: Level, message: String) -> Vec<(Level, Option<usize>)> {
+    if let Some(repeat) = last
+        .as_mut()
+        .filter(|repeat| repeat.level == level && repeat.message == message)
+    {
+        repeat.count += 1;
+        if repeat.count % REPEAT_INTERVAL != 0 {
+            return Vec::new();
+        }
+        repeat.shown = repeat.count;
+        return vec![(level, Some(repeat.count))];
+    }
+
+    let mut lines = Vec::new();
+    if let Some(repeat) = last.as_ref().filter(|repeat| repeat.count > repeat.shown) {
+        lines.push((repeat.level, Some(repeat.count)));
+    }
+    lines.push((level, None));
+    *last = Some(Repeat {
+        level,
+        message,
+        count: 1,
+        shown: 1,
+    });
+    lines
+}
+
+#[cfg_attr(target_arch = "wasm32", allow(unused))]
+pub fn init() -> Result<(), SetLoggerError> {
+    log::set_logger(&Logger).map(|()| log::set_max_level(LevelFilter::Info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeats_are_counted_while_they_happen() {
+        let mut last = None;
+        let mut lines = Vec::new();
+        for _ in 0..REPEAT_INTERVAL + 2 {
+            lines.extend(update(
+                &mut last,
+                Level::Error,
+                "Stack overflow.".to_string(),
+            ));
+        }
+        lines.extend(update(&mut last, Level::Info, "Reset.".to_string()));
+
+        assert_eq!(
+            lines,
+            [
+                (Level::Error, None),
+                (Level::Error, Some(REPEAT_INTERVAL)),
+                (Level::Error, Some(REPEAT_INTERVAL + 2)),
+                (Level::Info, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn shown_counts_are_not_repeated() {
+        let mut last = None;
+        let mut lines = Vec::new();
+        for _ in 0..REPEAT_INTERVAL {
+            lines.extend(update(
+                &mut last,
+                Level::Warn,
+                "Nothing to record.".to_string(),
+            ));
+        }
+        lines.extend(update(&mut last, Level::Info, "Reset.".to_string()));
+
+        assert_eq!(lines.last(), Some(&(Level::Info, None)));
+        assert_eq!(lines.len(), 3);
+    }
+}
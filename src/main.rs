@@ -7,11 +7,36 @@ use anyhow::Result;
 use app::Octarou;
 
 mod app;
+mod audio;
+#[cfg(not(target_arch = "wasm32"))]
+mod capture;
+#[cfg(not(target_arch = "wasm32"))]
+mod cli;
+mod database;
+#[cfg(not(target_arch = "wasm32"))]
+mod headless;
 mod interpreter;
+mod keymap;
+mod library;
+mod logs;
+#[cfg(not(target_arch = "wasm32"))]
+mod movie;
+mod post_mortem;
+mod settings;
+mod touch_keypad;
 
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> Result<()> {
-    egui_logger::init().expect("Failed to initialize egui_logger");
+    use clap::Parser;
+
+    let args = cli::Args::parse();
+    let program = args.rom.as_ref().map(app::Program::from_path).transpose()?;
+
+    if args.headless {
+        return headless::run(&args, program.expect("--headless requires a ROM"));
+    }
+
+    logs::init().expect("Failed to initialize logger");
 
     let native_options = eframe::NativeOptions {
         viewport: eframe::egui::ViewportBuilder::default()
@@ -24,7 +49,7 @@ fn main() -> Result<()> {
     eframe::run_native(
         "Octarou",
         native_options,
-        Box::new(|_cc| Box::new(Octarou::default())),
+        Box::new(move |cc| Box::new(Octarou::new(cc).with_args(args, program))),
     )
     .expect("Failed to start eframe");
 
@@ -36,8 +61,8 @@ fn main() {
     eframe::web::PanicHandler::install();
 
     let console_log = Box::new(eframe::WebLogger::new(log::LevelFilter::Debug));
-    let egui_log = Box::new(egui_logger::EguiLogger);
-    multi_log::MultiLogger::init(vec![console_log, egui_log], log::Level::Trace);
+    let app_log = Box::new(logs::Logger);
+    multi_log::MultiLogger::init(vec![console_log, app_log], log::Level::Trace);
 
     let web_options = eframe::WebOptions::default();
 
@@ -46,7 +71,7 @@ fn main() {
             .start(
                 "octarou_canvas_id",
                 web_options,
-                Box::new(|_cc| Box::new(app::Octarou::default())),
+                Box::new(|cc| Box::new(app::Octarou::new(cc))),
             )
             .await
             .expect("Failed to start eframe");
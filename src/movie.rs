@@ -0,0 +1,166 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+};
+
+use crate::app::{Mode, Program};
+use crate::interpreter::{Interpreter, MachineConfig, Quirks};
+
+pub const FRAME_RATE: u64 = 60;
+
+// A recording of the keypad state on every 60 Hz frame, along with everything else needed to
+// reproduce a run exactly: the ROM, the interpreter configuration and the RNG seed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Movie {
+    pub rom_hash: String,
+    pub mode: Mode,
+    pub quirks: Quirks,
+    #[serde(default)]
+    pub machine: MachineConfig,
+    pub speed: u64,
+    pub seed: u64,
+    pub frames: Vec<MovieFrame>,
+}
+
+// Keypad state as bitmasks, with bit N set if key N is down or was released this frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MovieFrame {
+    pub down: u16,
+    pub released: u16,
+}
+
+impl Movie {
+    pub fn new(
+        program: &Program,
+        mode: Mode,
+        quirks: Quirks,
+        machine: MachineConfig,
+        speed: u64,
+        seed: u64,
+    ) -> Self {
+        Self {
+            rom_hash: program.hash().to_string(),
+            mode,
+            quirks,
+            machine,
+            speed,
+            seed,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| anyhow::anyhow!("{} is not a valid movie: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        serde_json::to_writer(BufWriter::new(File::create(path)?), self)?;
+        Ok(())
+    }
+
+    // Builds a fresh interpreter in the state the movie was recorded from.
+    pub fn interpreter(&self, program: &Program) -> Result<Box<dyn Interpreter>> {
+        if program.hash() != self.rom_hash {
+            anyhow::bail!(
+                "Movie was recorded with a different ROM (SHA-1 {}) than {}",
+                self.rom_hash,
+                program.filename()
+            );
+        }
+        Ok(self
+            .mode
+            .interpreter(program.data(), self.quirks, &self.machine, self.seed)?)
+    }
+
+    pub fn instructions_per_frame(&self) -> u64 {
+        (self.speed / FRAME_RATE).max(1)
+    }
+
+    pub fn record(&mut self, keys_down: &[bool; 16], keys_released: &[bool; 16]) {
+        self.frames.push(MovieFrame {
+            down: pack(keys_down),
+            released: pack(keys_released),
+        });
+    }
+
+    pub fn keys(&self, frame: usize) -> Option<([bool; 16], [bool; 16])> {
+        self.frames
+            .get(frame)
+            .map(|frame| (unpack(frame.down), unpack(frame.released)))
+    }
+}
+
+fn pack(keys: &[bool; 16]) -> u16 {
+    keys.iter()
+        .enumerate()
+        .filter(|(_, &key)| key)
+        .fold(0, |mask, (i, _)| mask | 1 << i)
+}
+
+fn unpack(mask: u16) -> [bool; 16] {
+    std::array::from_fn(|i| mask & 1 << i != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_sets_one_bit_per_key() {
+        let mut keys = [false; 16];
+        keys[0x0] = true;
+        keys[0x5] = true;
+        keys[0xF] = true;
+
+        assert_eq!(pack(&keys), 0b1000_0000_0010_0001);
+        assert_eq!(unpack(pack(&keys)), keys);
+        assert_eq!(unpack(0), [false; 16]);
+        assert_eq!(unpack(u16::MAX), [true; 16]);
+    }
+
+    #[test]
+    fn keys_replay_recorded_frames() {
+        let program = Program::new("test.ch8", &[0x12, 0x00]).unwrap();
+        let mut movie = Movie::new(
+            &program,
+            Mode::Chip8,
+            Mode::Chip8.default_quirks(),
+            MachineConfig::new(),
+            600,
+            0,
+        );
+        let (mut down, mut released) = ([false; 16], [false; 16]);
+        down[0x4] = true;
+        released[0xC] = true;
+        movie.record(&down, &released);
+        movie.record(&[false; 16], &[false; 16]);
+
+        assert_eq!(movie.keys(0), Some((down, released)));
+        assert_eq!(movie.keys(1), Some(([false; 16], [false; 16])));
+        assert_eq!(movie.keys(2), None);
+        assert_eq!(movie.instructions_per_frame(), 10);
+    }
+
+    #[test]
+    fn interpreter_requires_the_recorded_rom() {
+        let program = Program::new("test.ch8", &[0x12, 0x00]).unwrap();
+        let other = Program::new("other.ch8", &[0x12, 0x02]).unwrap();
+        let movie = Movie::new(
+            &program,
+            Mode::Chip8,
+            Mode::Chip8.default_quirks(),
+            MachineConfig::new(),
+            600,
+            0,
+        );
+
+        assert!(movie.interpreter(&program).is_ok());
+        assert!(movie.interpreter(&other).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+use std::io::{self, Write};
+
+use crate::interpreter::{Interpreter, InterpreterError, Snapshot};
+
+// Instructions shown on either side of the faulting one.
+const DISASSEMBLY_CONTEXT: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultPolicy {
+    // Stop and show the post-mortem.
+    Halt,
+    // Log the fault and carry on with the next instruction.
+    Skip,
+    // Log the fault and restart the program.
+    Reset,
+}
+
+impl FaultPolicy {
+    pub const ALL: [FaultPolicy; 3] = [FaultPolicy::Halt, FaultPolicy::Skip, FaultPolicy::Reset];
+}
+
+pub struct PostMortem {
+    pub error: InterpreterError,
+    pub snapshot: Snapshot,
+    // The instructions around the faulting one, as (address, opcode, text) triples.
+    pub disassembly: Vec<(usize, u16, String)>,
+}
+
+impl PostMortem {
+    pub fn new(error: InterpreterError, interpreter: &dyn Interpreter) -> Self {
+        let snapshot = interpreter.snapshot();
+        let disassembly = disassemble(&error, &snapshot, interpreter);
+        Self {
+            error,
+            snapshot,
+            disassembly,
+        }
+    }
+
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        let snapshot = &self.snapshot;
+
+        writeln!(writer, "Fault: {}", self.error.kind)?;
+        writeln!(writer, "Variant: {:?}", self.error.variant)?;
+        writeln!(writer, "Address: {:#06x}", self.error.address)?;
+        match self.error.opcode {
+            Some(opcode) => writeln!(writer, "Opcode: {:04X}", opcode)?,
+            None => writeln!(writer, "Opcode: out of memory")?,
+        }
+
+        writeln!(writer, "\nRegisters:")?;
+        for (i, value) in snapshot.registers.iter().enumerate() {
+            writeln!(writer, "  V{:X} = {:#04x}", i, value)?;
+        }
+        writeln!(writer, "  I  = {:#06x}", snapshot.index)?;
+        writeln!(writer, "  PC = {:#06x}", snapshot.pc)?;
+        writeln!(writer, "  DT = {:#04x}", snapshot.delay_timer)?;
+        writeln!(writer, "  ST = {:#04x}", snapshot.sound_timer)?;
+
+        writeln!(writer, "\nStack:")?;
+        if snapshot.stack.is_empty() {
+            writeln!(writer, "  (empty)")?;
+        }
+        for address in snapshot.stack.iter().rev() {
+            writeln!(writer, "  {:#06x}", address)?;
+        }
+
+        writeln!(writer, "\nDisassembly:")?;
+        for &(address, opcode, ref text) in &self.disassembly {
+            let marker = if address == self.error.address {
+                ">"
+            } else {
+                " "
+            };
+            writeln!(
+                writer,
+                "{} {:#06x}  {:04X}  {}",
+                marker, address, opcode, text
+            )?;
+        }
+
+        writeln!(writer, "\nMemory:")?;
+        for (start, memory) in &snapshot.memory {
+            for (row, bytes) in memory.chunks(16).enumerate() {
+                let hex = bytes
+                    .iter()
+                    .map(|byte| format!("{:02X}", byte))
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                writeln!(writer, "{:#06x}  {}", start + row * 16, hex)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        let snapshot = &self.snapshot;
+
+        ui.colored_label(ui.visuals().error_fg_color, self.error.kind.to_string());
+        ui.monospace(match self.error.opcode {
+            Some(opcode) => format!(
+                "{:04X} at {:#06x} ({:?})",
+                opcode, self.error.address, self.error.variant
+            ),
+            None => format!(
+                "No instruction at {:#06x} ({:?})",
+                self.error.address, self.error.variant
+            ),
+        });
+        ui.separator();
+
+        ui.horizontal_top(|ui| {
+            ui.vertical(|ui| {
+                ui.strong("Registers");
+                egui::Grid::new("post-mortem-registers")
+                    .num_columns(4)
+                    .show(ui, |ui| {
+                        for (i, value) in snapshot.registers.iter().enumerate() {
+                            ui.monospace(format!("V{:X} {:02X}", i, value));
+                            if i % 4 == 3 {
+                                ui.end_row();
+                            }
+                        }
+                    });
+                ui.monospace(format!(
+                    "I {:#06x}  DT {:02X}  ST {:02X}",
+                    snapshot.index, snapshot.delay_timer, snapshot.sound_timer
+                ));
+
+                ui.add_space(6.0);
+                ui.strong("Stack");
+                if snapshot.stack.is_empty() {
+                    ui.weak("Empty");
+                }
+                for address in snapshot.stack.iter().rev() {
+                    ui.monospace(format!("{:#06x}", address));
+                }
+            });
+
+            ui.separator();
+
+            ui.vertical(|ui| {
+                ui.strong("Disassembly");
+                for &(address, opcode, ref text) in &self.disassembly {
+                    let line = format!("{:#06x}  {:04X}  {}", address, opcode, text);
+                    match address == self.error.address {
+                        true => ui.colored_label(
+                            ui.visuals().error_fg_color,
+                            egui::RichText::new(line).monospace(),
+                        ),
+                        false => ui.monospace(line),
+                    };
+                }
+            });
+        });
+    }
+}
+
+fn opcode_at(snapshot: &Snapshot, address: usize) -> Option<u16> {
+    snapshot.memory.iter().find_map(|(start, memory)| {
+        let offset = address.checked_sub(*start)?;
+        let bytes = memory.get(offset..offset + 2)?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    })
+}
+
+// Disassembles the instructions around the faulting one the way the interpreter decodes them.
+// Data mixed in with the code is disassembled all the same.
+fn disassemble(
+    error: &InterpreterError,
+    snapshot: &Snapshot,
+    interpreter: &dyn Interpreter,
+) -> Vec<(usize, u16, String)> {
+    let start = error.address.saturating_sub(2 * DISASSEMBLY_CONTEXT);
+    (start..=error.address + 2 * DISASSEMBLY_CONTEXT)
+        .step_by(2)
+        .filter_map(|address| {
+            let opcode = opcode_at(snapshot, address)?;
+            let text = match interpreter.decode(opcode) {
+                Some(instruction) => format!("{:?}", instruction),
+                None => "???".to_string(),
+            };
+            Some((address, opcode, text))
+        })
+        .collect()
+}
@@ -0,0 +1,246 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::app::Mode;
+use crate::audio::Tone;
+use crate::interpreter::MachineConfig;
+use crate::keymap::Keymap;
+use crate::touch_keypad::Visibility;
+
+pub const SETTINGS_KEY: &str = "settings";
+// Older versions only saved the keymap, under its own key.
+const KEYMAP_KEY: &str = "keymap";
+const MAX_RECENT_FILES: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Palette {
+    pub foreground: egui::Color32,
+    pub background: egui::Color32,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            foreground: egui::Color32::WHITE,
+            background: egui::Color32::from_gray(10),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RomSettings {
+    pub mode: Mode,
+    pub speed: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub mode: Mode,
+    pub auto_detect: bool,
+    pub speed: u64,
+    pub machine: MachineConfig,
+    pub muted: bool,
+    pub tone: Tone,
+    pub palette: Palette,
+    pub capture_scale: usize,
+    pub keymap: Keymap,
+    pub touch_keypad: Visibility,
+    pub last_directory: Option<PathBuf>,
+    pub recent_files: Vec<PathBuf>,
+    pub library_directory: Option<PathBuf>,
+    // Folder holding `programs.json` and `sha1-hashes.json` from the CHIP-8 program database.
+    pub database_directory: Option<PathBuf>,
+    pub rom_settings: HashMap<PathBuf, RomSettings>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            mode: Mode::Chip8,
+            auto_detect: true,
+            speed: 700,
+            machine: MachineConfig::default(),
+            muted: false,
+            tone: Tone::default(),
+            palette: Palette::default(),
+            capture_scale: 8,
+            keymap: Keymap::default(),
+            touch_keypad: Visibility::Auto,
+            last_directory: None,
+            recent_files: Vec::new(),
+            library_directory: None,
+            database_directory: None,
+            rom_settings: HashMap::new(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        let Some(storage) = storage else {
+            return Self::default();
+        };
+
+        eframe::get_value(storage, SETTINGS_KEY).unwrap_or_else(|| Self {
+            keymap: eframe::get_value(storage, KEYMAP_KEY).unwrap_or_default(),
+            ..Self::default()
+        })
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, SETTINGS_KEY, self);
+    }
+
+    pub fn add_recent_file(&mut self, path: &Path) {
+        self.recent_files.retain(|p| p != path);
+        self.recent_files.insert(0, path.to_path_buf());
+        self.recent_files.truncate(MAX_RECENT_FILES);
+    }
+
+    // The settings as they are saved, without the overrides of this session.
+    pub fn persisted(&self, overrides: &Overrides) -> Self {
+        let mut settings = self.clone();
+        overrides.restore(&mut settings);
+        settings
+    }
+
+    pub fn remember_rom(&mut self, path: &Path, overrides: &Overrides) {
+        let (mut mode, mut speed) = (self.mode, self.speed);
+        if let Some(mode_override) = &overrides.mode {
+            mode_override.restore(&mut mode);
+        }
+        if let Some(speed_override) = &overrides.speed {
+            speed_override.restore(&mut speed);
+        }
+        self.rom_settings
+            .insert(path.to_path_buf(), RomSettings { mode, speed });
+    }
+
+    // Returns whether there were settings to restore.
+    pub fn restore_rom(&mut self, path: &Path) -> bool {
+        let Some(rom_settings) = self.rom_settings.get(path) else {
+            return false;
+        };
+        self.mode = rom_settings.mode;
+        self.speed = rom_settings.speed;
+        true
+    }
+}
+
+// A setting given on the command line. It only lasts for the session, so the value it replaced is
+// saved in its place unless the setting is changed in the meantime.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Override<T> {
+    value: T,
+    replaced: T,
+}
+
+impl<T: Clone + PartialEq> Override<T> {
+    pub fn apply(setting: &mut T, value: T) -> Self {
+        let replaced = std::mem::replace(setting, value.clone());
+        Self { value, replaced }
+    }
+
+    pub fn restore(&self, setting: &mut T) {
+        if *setting == self.value {
+            *setting = self.replaced.clone();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Overrides {
+    // The mode and speed only apply to the program given on the command line.
+    pub mode: Option<Override<Mode>>,
+    pub speed: Option<Override<u64>>,
+    pub muted: Option<Override<bool>>,
+    pub machine: Option<Override<MachineConfig>>,
+}
+
+impl Overrides {
+    fn restore(&self, settings: &mut Settings) {
+        if let Some(mode) = &self.mode {
+            mode.restore(&mut settings.mode);
+        }
+        if let Some(speed) = &self.speed {
+            speed.restore(&mut settings.speed);
+        }
+        if let Some(muted) = &self.muted {
+            muted.restore(&mut settings.muted);
+        }
+        if let Some(machine) = &self.machine {
+            machine.restore(&mut settings.machine);
+        }
+    }
+
+    // Applies the mode and speed again over whatever the program would otherwise run with.
+    pub fn reapply_program(&mut self, settings: &mut Settings) {
+        if let Some(mode) = &mut self.mode {
+            *mode = Override::apply(&mut settings.mode, mode.value);
+        }
+        if let Some(speed) = &mut self.speed {
+            *speed = Override::apply(&mut settings.speed, speed.value);
+        }
+    }
+
+    // Drops the mode and speed once another program is opened.
+    pub fn clear_program(&mut self, settings: &mut Settings) {
+        if let Some(mode) = self.mode.take() {
+            mode.restore(&mut settings.mode);
+        }
+        if let Some(speed) = self.speed.take() {
+            speed.restore(&mut settings.speed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::Preset;
+
+    #[derive(Default)]
+    struct MemoryStorage(HashMap<String, String>);
+
+    impl eframe::Storage for MemoryStorage {
+        fn get_string(&self, key: &str) -> Option<String> {
+            self.0.get(key).cloned()
+        }
+
+        fn set_string(&mut self, key: &str, value: String) {
+            self.0.insert(key.to_string(), value);
+        }
+
+        fn flush(&mut self) {}
+    }
+
+    #[test]
+    fn load_migrates_saved_keymap() {
+        let mut storage = MemoryStorage::default();
+        let keymap = Keymap::from_preset(Preset::Azerty);
+        eframe::set_value(&mut storage, KEYMAP_KEY, &keymap);
+
+        assert_eq!(Settings::load(Some(&storage)).keymap, keymap);
+    }
+
+    #[test]
+    fn load_prefers_saved_settings() {
+        let mut storage = MemoryStorage::default();
+        eframe::set_value(
+            &mut storage,
+            KEYMAP_KEY,
+            &Keymap::from_preset(Preset::Azerty),
+        );
+        let settings = Settings {
+            keymap: Keymap::from_preset(Preset::Qwertz),
+            ..Settings::default()
+        };
+        settings.save(&mut storage);
+
+        assert_eq!(Settings::load(Some(&storage)).keymap, settings.keymap);
+    }
+}
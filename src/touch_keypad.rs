@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::keymap::{Keymap, VIP_LAYOUT};
+
+const KEY_SIZE: f32 = 56.0;
+const KEY_SPACING: f32 = 6.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Visibility {
+    // Shown once the device has reported a touch.
+    Auto,
+    Shown,
+    Hidden,
+}
+
+impl Visibility {
+    pub const ALL: [Visibility; 3] = [Visibility::Auto, Visibility::Shown, Visibility::Hidden];
+
+    pub fn is_visible(self, touch_seen: bool) -> bool {
+        match self {
+            Visibility::Auto => touch_seen,
+            Visibility::Shown => true,
+            Visibility::Hidden => false,
+        }
+    }
+}
+
+// An on-screen COSMAC VIP keypad. Every finger is tracked separately, so several keys can be held
+// at once, and the mouse works as one more finger.
+#[derive(Debug)]
+pub struct TouchKeypad {
+    // Fingers currently down, keyed by device and touch ID.
+    touches: HashMap<(u64, u64), egui::Pos2>,
+    // Where each key was drawn on the previous frame, indexed by hex key.
+    rects: [egui::Rect; 16],
+    held: [bool; 16],
+    // Whether the device has reported a touch since the app started.
+    touch_seen: bool,
+}
+
+impl Default for TouchKeypad {
+    fn default() -> Self {
+        Self {
+            touches: HashMap::new(),
+            rects: [egui::Rect::NOTHING; 16],
+            held: [false; 16],
+            touch_seen: false,
+        }
+    }
+}
+
+impl TouchKeypad {
+    pub fn touch_seen(&self) -> bool {
+        self.touch_seen
+    }
+
+    // Forgets where the keys were drawn, so nothing is pressed while the keypad is hidden.
+    pub fn hide(&mut self) {
+        self.rects = [egui::Rect::NOTHING; 16];
+    }
+
+    // Returns the keys held and released this frame, in the same form as `Keymap::keys_down`
+    // and `Keymap::keys_released`, so the two can be combined. This must run every frame, even
+    // while the keypad is hidden, so that fingers lifted in the meantime release their keys.
+    pub fn input(&mut self, input: &egui::InputState) -> ([bool; 16], [bool; 16]) {
+        let mut released = [false; 16];
+
+        for event in &input.events {
+            if let egui::Event::Touch {
+                device_id,
+                id,
+                phase,
+                pos,
+                ..
+            } = *event
+            {
+                self.touch_seen = true;
+                match phase {
+                    egui::TouchPhase::Start | egui::TouchPhase::Move => {
+                        self.touches.insert((device_id.0, id.0), pos);
+                    }
+                    egui::TouchPhase::End | egui::TouchPhase::Cancel => {
+                        self.touches.remove(&(device_id.0, id.0));
+                        if let Some(key) = self.key_at(pos) {
+                            released[key] = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        let pointer = input.pointer.interact_pos();
+        let mut held = [false; 16];
+        for pos in self
+            .touches
+            .values()
+            .chain(pointer.filter(|_| input.pointer.primary_down()).iter())
+        {
+            if let Some(key) = self.key_at(*pos) {
+                held[key] = true;
+            }
+        }
+        if input.pointer.primary_released() {
+            if let Some(key) = pointer.and_then(|pos| self.key_at(pos)) {
+                released[key] = true;
+            }
+        }
+
+        // Sliding a finger off a key releases it.
+        for key in 0..16 {
+            released[key] |= self.held[key] && !held[key];
+        }
+        self.held = held;
+
+        (held, released)
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, keymap: &Keymap) {
+        let size = egui::Vec2::splat(4.0 * KEY_SIZE + 3.0 * KEY_SPACING);
+        let (rect, _) = ui.allocate_exact_size(size, egui::Sense::click_and_drag());
+        let painter = ui.painter_at(rect);
+        let visuals = ui.visuals();
+
+        for (row, keys) in VIP_LAYOUT.iter().enumerate() {
+            for (column, &key) in keys.iter().enumerate() {
+                let min =
+                    rect.min + egui::vec2(column as f32, row as f32) * (KEY_SIZE + KEY_SPACING);
+                let key_rect = egui::Rect::from_min_size(min, egui::Vec2::splat(KEY_SIZE));
+                self.rects[key as usize] = key_rect;
+
+                let style = match self.held[key as usize] {
+                    true => &visuals.widgets.active,
+                    false => &visuals.widgets.inactive,
+                };
+                painter.rect(
+                    key_rect,
+                    style.rounding,
+                    style.weak_bg_fill,
+                    style.bg_stroke,
+                );
+                painter.text(
+                    key_rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    format!("{:X}", key),
+                    egui::FontId::monospace(KEY_SIZE * 0.45),
+                    style.fg_stroke.color,
+                );
+
+                // The keyboard key bound to this hex key, as a reference.
+                if let Some(binding) = keymap.bindings(key).first() {
+                    painter.text(
+                        key_rect.center_bottom() - egui::vec2(0.0, 4.0),
+                        egui::Align2::CENTER_BOTTOM,
+                        binding.symbol_or_name(),
+                        egui::FontId::proportional(KEY_SIZE * 0.2),
+                        visuals.weak_text_color(),
+                    );
+                }
+            }
+        }
+    }
+
+    fn key_at(&self, pos: egui::Pos2) -> Option<usize> {
+        self.rects.iter().position(|rect| rect.contains(pos))
+    }
+}